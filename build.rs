@@ -0,0 +1,108 @@
+//! # Build Script
+//!
+//! Identifies the compiler linked into the CLI for build fingerprints:
+//!
+//! - `QUANTUM_COMPILER_VERSION`: version of the quantum-compiler crate
+//! - `QUANTUM_BUILD_COMMIT`: the git commit the CLI is built from, with a
+//!   `-dirty` suffix when the compiler sources have uncommitted changes
+//!
+//! Either is `unknown` when it cannot be determined. The compiler is built
+//! from the same repository, so together they identify it unless the
+//! commit is unknown or dirty.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Sources of the compiler, relative to the package directory
+const COMPILER_SOURCES: &[&str] = &["../quantum-compiler", "../../crates/silver-core"];
+
+fn main() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let sources = COMPILER_SOURCES.iter().map(|source| manifest_dir.join(source)).collect::<Vec<_>>();
+    
+    let version = compiler_version(&sources[0]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=QUANTUM_COMPILER_VERSION={}", version);
+    
+    let commit = match git(&["rev-parse", "HEAD"]) {
+        Some(commit) if is_dirty(&sources) => format!("{}-dirty", commit),
+        Some(commit) => commit,
+        None => "unknown".to_string(),
+    };
+    println!("cargo:rustc-env=QUANTUM_BUILD_COMMIT={}", commit);
+    println!("cargo:rerun-if-changed=build.rs");
+    
+    // Run again when the compiler changes, to notice uncommitted changes
+    for source in &sources {
+        println!("cargo:rerun-if-changed={}", source.display());
+    }
+    
+    // Run again when HEAD moves to another commit
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={}", Path::new(&git_dir).join("HEAD").display());
+        println!("cargo:rerun-if-changed={}", Path::new(&git_dir).join("index").display());
+    }
+    
+    if let (Some(common_dir), Some(head)) = (git(&["rev-parse", "--git-common-dir"]), git(&["symbolic-ref", "-q", "HEAD"])) {
+        let common_dir = manifest_dir.join(common_dir);
+        println!("cargo:rerun-if-changed={}", common_dir.join(head).display());
+        println!("cargo:rerun-if-changed={}", common_dir.join("packed-refs").display());
+    }
+}
+
+/// Read the version of the compiler crate from its manifest, following
+/// `version.workspace = true` to the workspace manifest
+fn compiler_version(compiler_dir: &Path) -> Option<String> {
+    let manifest = read_manifest(&compiler_dir.join("Cargo.toml"))?;
+    let version = manifest.get("package")?.get("version")?;
+    
+    if let Some(version) = version.as_str() {
+        return Some(version.to_string());
+    }
+    
+    if version.get("workspace")?.as_bool() != Some(true) {
+        return None;
+    }
+    
+    let compiler_dir = compiler_dir.canonicalize().ok()?;
+    
+    compiler_dir.ancestors().skip(1).find_map(|dir| {
+        let workspace = read_manifest(&dir.join("Cargo.toml"))?;
+        let version = workspace.get("workspace")?.get("package")?.get("version")?;
+        
+        version.as_str().map(str::to_string)
+    })
+}
+
+/// Parse a Cargo.toml, noting that the build depends on it
+fn read_manifest(path: &Path) -> Option<toml::Table> {
+    let content = std::fs::read_to_string(path).ok()?;
+    println!("cargo:rerun-if-changed={}", path.display());
+    
+    content.parse().ok()
+}
+
+/// Check whether any of the compiler sources differ from the commit
+fn is_dirty(sources: &[PathBuf]) -> bool {
+    let sources = sources.iter().map(|source| source.display().to_string()).collect::<Vec<_>>();
+    let mut args = vec!["status", "--porcelain", "--"];
+    args.extend(sources.iter().map(String::as_str));
+    
+    // Without git status the commit may not describe the sources either
+    git(&args).is_none_or(|status| !status.is_empty())
+}
+
+/// Run git in the package directory and return its trimmed output
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .output()
+        .ok()?;
+    
+    if !output.status.success() {
+        return None;
+    }
+    
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,636 @@
+//! # Package Cache
+//!
+//! The download cache shared by every package on the machine, in the
+//! directory given by [`crate::paths::cache_dir`]:
+//!
+//! - `<name>-<version>/` with `<name>-<version>.tar`: packages of the
+//!   default registry, extracted next to their archive
+//! - `registries/<source>/`: packages of other registries, same layout
+//! - `git/db/<repo>/`: bare git databases
+//! - `git/checkouts/<repo>/<commit>/`: checked out commits
+//!
+//! Access is serialized across processes with a file lock, held while
+//! entries are created, used or removed but not while registry packages
+//! are downloaded or git repositories are fetched. The last time each
+//! entry was used is recorded so old entries can be collected. Entries are
+//! written under a hidden name and renamed into place once complete, so an
+//! interrupted download never leaves a partial entry.
+
+use crate::checksum::{self, PackageChecksums};
+use crate::config::DEFAULT_REGISTRY_SOURCE;
+use crate::version;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use fs4::fs_std::FileExt;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Lock file serializing access to the cache
+const LOCK_FILE: &str = ".package-cache.lock";
+
+/// Last time each entry was used, by relative path
+const LAST_USE_FILE: &str = ".last-use.json";
+
+/// Maximum total size of the files in a package archive
+const MAX_UNPACKED_SIZE: u64 = 256 << 20;
+
+/// Maximum number of entries in a package archive
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+
+/// Serializes holders of the cache lock within this process, so that
+/// waiting for the file lock always means another process holds it
+static PROCESS_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Exclusive lock on the cache, released when dropped
+pub struct CacheLock {
+    _file: File,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl CacheLock {
+    /// Lock the cache, waiting for other processes that hold the lock
+    /// without blocking the async runtime
+    pub async fn acquire(cache_dir: &Path) -> Result<Self> {
+        let guard = PROCESS_LOCK.lock().await;
+        let cache_dir = cache_dir.to_path_buf();
+        let file = tokio::task::spawn_blocking(move || lock_file(&cache_dir))
+            .await
+            .context("Failed to lock the package cache")??;
+        
+        Ok(Self { _file: file, _guard: guard })
+    }
+    
+    /// Lock the cache from blocking code, such as a
+    /// [`tokio::task::spawn_blocking`] task.
+    ///
+    /// # Panics
+    /// When called from async code
+    pub fn acquire_blocking(cache_dir: &Path) -> Result<Self> {
+        let guard = PROCESS_LOCK.blocking_lock();
+        let file = lock_file(cache_dir)?;
+        
+        Ok(Self { _file: file, _guard: guard })
+    }
+}
+
+/// Open the lock file of the cache and lock it, waiting if needed
+fn lock_file(cache_dir: &Path) -> Result<File> {
+    std::fs::create_dir_all(cache_dir)
+        .with_context(|| format!("Failed to create {}", cache_dir.display()))?;
+    
+    let path = cache_dir.join(LOCK_FILE);
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    
+    let locked = FileExt::try_lock_exclusive(&file)
+        .with_context(|| format!("Failed to lock {}", path.display()))?;
+    
+    if !locked {
+        println!("{} waiting for file lock on package cache", "Blocking".cyan().bold());
+        FileExt::lock_exclusive(&file)
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+    }
+    
+    Ok(file)
+}
+
+/// Extract a package archive into the cache.
+///
+/// The archive is unpacked next to `dest` under a hidden name and renamed
+/// into place once every entry was written, replacing any previous entry.
+/// Only plain files and directories with relative paths inside the package
+/// are accepted.
+///
+/// # Arguments
+/// * `archive` - The tar archive
+/// * `dest` - Directory of the cache entry
+pub fn extract_archive(archive: &[u8], dest: &Path) -> Result<()> {
+    extract_archive_with_limit(archive, dest, MAX_UNPACKED_SIZE)
+}
+
+fn extract_archive_with_limit(archive: &[u8], dest: &Path, max_size: u64) -> Result<()> {
+    let partial = partial_path(dest);
+    
+    if partial.exists() {
+        std::fs::remove_dir_all(&partial)
+            .with_context(|| format!("Failed to remove {}", partial.display()))?;
+    }
+    
+    std::fs::create_dir_all(&partial)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+    
+    if let Err(error) = unpack(archive, &partial, max_size) {
+        let _ = std::fs::remove_dir_all(&partial);
+        return Err(error).with_context(|| format!("Invalid package archive for {}", dest.display()));
+    }
+    
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)
+            .with_context(|| format!("Failed to remove {}", dest.display()))?;
+    }
+    
+    std::fs::rename(&partial, dest)
+        .with_context(|| format!("Failed to move {} into place", dest.display()))
+}
+
+/// Write a file of the cache, renaming it into place once complete
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let partial = partial_path(path);
+    
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    
+    std::fs::write(&partial, contents)
+        .with_context(|| format!("Failed to write {}", partial.display()))?;
+    std::fs::rename(&partial, path)
+        .with_context(|| format!("Failed to move {} into place", path.display()))
+}
+
+/// Hidden sibling of a path that it is written to before being complete
+fn partial_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!(".{}.partial", file_name(path)))
+}
+
+/// Unpack validated archive entries into a directory
+fn unpack(archive: &[u8], dir: &Path, max_size: u64) -> Result<()> {
+    let mut tar = tar::Archive::new(archive);
+    let mut size = 0u64;
+    
+    for (index, entry) in tar.entries().context("Failed to read archive")?.enumerate() {
+        let mut entry = entry.context("Failed to read archive entry")?;
+        let path = entry.path()?.into_owned();
+        
+        if index >= MAX_ARCHIVE_ENTRIES {
+            anyhow::bail!("archive has more than {} entries", MAX_ARCHIVE_ENTRIES);
+        }
+        
+        if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            anyhow::bail!("entry {} is not a relative path inside the package", path.display());
+        }
+        
+        let entry_type = entry.header().entry_type();
+        
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            anyhow::bail!("entry {} is not a regular file or directory", path.display());
+        }
+        
+        size = size.saturating_add(entry.size());
+        
+        if size > max_size {
+            anyhow::bail!("archive unpacks to more than {}", format_size(max_size));
+        }
+        
+        if !entry.unpack_in(dir)? {
+            anyhow::bail!("entry {} is outside the package", path.display());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Kind of a cache entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// An extracted registry package and its archive
+    Registry,
+    /// A checkout of a git commit
+    Git,
+}
+
+/// A package stored in the cache
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// Whether the entry is a registry package or a git checkout
+    pub kind: EntryKind,
+    /// Registry source name, or the name of the git database
+    pub source: String,
+    /// Package name, or repository name for git checkouts
+    pub name: String,
+    /// Package version, or abbreviated commit for git checkouts
+    pub version: String,
+    /// Directory holding the files
+    pub path: PathBuf,
+    /// Archive of a registry package
+    pub archive: Option<PathBuf>,
+    /// Size on disk in bytes
+    pub size: u64,
+    /// Last time the entry was used by a build
+    pub last_used: SystemTime,
+}
+
+impl CacheEntry {
+    /// Remove the entry from the cache
+    pub fn remove(&self) -> Result<()> {
+        std::fs::remove_dir_all(&self.path)
+            .with_context(|| format!("Failed to remove {}", self.path.display()))?;
+        
+        if let Some(archive) = &self.archive {
+            std::fs::remove_file(archive)
+                .with_context(|| format!("Failed to remove {}", archive.display()))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Re-hash the entry's files and compare them with what was recorded.
+    ///
+    /// A registry package must match the archive it was extracted from; a
+    /// git checkout must match the checksums written when it was created.
+    pub fn verify(&self) -> Result<()> {
+        match (self.kind, &self.archive) {
+            (EntryKind::Registry, Some(archive_path)) => {
+                let archive = std::fs::read(archive_path)
+                    .with_context(|| format!("Failed to read {}", archive_path.display()))?;
+                
+                if checksum::archive_tree_checksum(&archive)? != checksum::tree_checksum(&self.path)? {
+                    anyhow::bail!("files differ from {}", archive_path.display());
+                }
+                
+                Ok(())
+            }
+            (EntryKind::Registry, None) => anyhow::bail!("archive is missing"),
+            (EntryKind::Git, _) => PackageChecksums::load(&self.path)?.verify(&self.path),
+        }
+    }
+}
+
+/// List every complete entry in the cache, ordered by source, name and version
+pub fn entries(cache_dir: &Path) -> Result<Vec<CacheEntry>> {
+    let last_use = load_last_use(cache_dir)?;
+    let mut entries = Vec::new();
+    
+    registry_entries(cache_dir, cache_dir, DEFAULT_REGISTRY_SOURCE, &last_use, &mut entries)?;
+    
+    for source in subdirectories(&cache_dir.join("registries"))? {
+        let name = file_name(&source);
+        registry_entries(cache_dir, &source, &name, &last_use, &mut entries)?;
+    }
+    
+    for repo in subdirectories(&cache_dir.join("git").join("checkouts"))? {
+        let ident = file_name(&repo);
+        let name = ident.rsplit_once('-').map_or(ident.as_str(), |(name, _)| name).to_string();
+        
+        for checkout in subdirectories(&repo)? {
+            // Checkouts without checksums were interrupted
+            if !checkout.join(checksum::CHECKSUM_FILE).exists() {
+                continue;
+            }
+            
+            entries.push(CacheEntry {
+                kind: EntryKind::Git,
+                source: ident.clone(),
+                name: name.clone(),
+                version: file_name(&checkout),
+                size: dir_size(&checkout)?,
+                last_used: last_used(cache_dir, &checkout, &last_use)?,
+                path: checkout,
+                archive: None,
+            });
+        }
+    }
+    
+    entries.sort_by(|a, b| (&a.source, &a.name, &a.version).cmp(&(&b.source, &b.name, &b.version)));
+    
+    Ok(entries)
+}
+
+/// Collect the extracted packages in a registry cache directory
+fn registry_entries(
+    cache_dir: &Path,
+    dir: &Path,
+    source: &str,
+    last_use: &BTreeMap<String, u64>,
+    entries: &mut Vec<CacheEntry>,
+) -> Result<()> {
+    for path in subdirectories(dir)? {
+        let Some((name, version)) = split_name_version(&file_name(&path)) else {
+            continue;
+        };
+        
+        let archive = dir.join(format!("{}-{}.tar", name, version));
+        let mut size = dir_size(&path)?;
+        
+        if archive.exists() {
+            size += archive.metadata()?.len();
+        }
+        
+        entries.push(CacheEntry {
+            kind: EntryKind::Registry,
+            source: source.to_string(),
+            name,
+            version,
+            size,
+            last_used: last_used(cache_dir, &path, last_use)?,
+            archive: archive.exists().then_some(archive),
+            path,
+        });
+    }
+    
+    Ok(())
+}
+
+/// Split `<name>-<version>` at the first dash followed by a valid version
+fn split_name_version(dir_name: &str) -> Option<(String, String)> {
+    dir_name
+        .match_indices('-')
+        .map(|(index, _)| (&dir_name[..index], &dir_name[index + 1..]))
+        .find(|(name, version)| !name.is_empty() && version::parse_version(version).is_ok())
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+}
+
+/// Record that cache entries were used now.
+///
+/// Paths outside the cache, such as path dependencies, are ignored.
+pub fn record_use<'p>(cache_dir: &Path, paths: impl IntoIterator<Item = &'p Path>) -> Result<()> {
+    let mut last_use = load_last_use(cache_dir)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut changed = false;
+    
+    for path in paths {
+        if let Ok(relative) = path.strip_prefix(cache_dir) {
+            last_use.insert(key(relative), now);
+            changed = true;
+        }
+    }
+    
+    if changed {
+        save_last_use(cache_dir, &last_use)?;
+    }
+    
+    Ok(())
+}
+
+/// Forget the last use of entries that no longer exist
+pub fn prune_last_use(cache_dir: &Path) -> Result<()> {
+    let mut last_use = load_last_use(cache_dir)?;
+    last_use.retain(|relative, _| cache_dir.join(relative).exists());
+    
+    save_last_use(cache_dir, &last_use)
+}
+
+/// Remove git databases that no longer have any checkouts.
+///
+/// # Returns
+/// The number of bytes freed
+pub fn remove_unused_databases(cache_dir: &Path) -> Result<u64> {
+    let git_dir = cache_dir.join("git");
+    let mut freed = 0;
+    
+    for db in subdirectories(&git_dir.join("db"))? {
+        let checkouts = git_dir.join("checkouts").join(file_name(&db));
+        
+        if subdirectories(&checkouts)?.is_empty() {
+            freed += dir_size(&db)?;
+            std::fs::remove_dir_all(&db)
+                .with_context(|| format!("Failed to remove {}", db.display()))?;
+            
+            if checkouts.exists() {
+                std::fs::remove_dir_all(&checkouts)?;
+            }
+        }
+    }
+    
+    Ok(freed)
+}
+
+/// Get the total size of the files below a directory
+pub fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    
+    Ok(size)
+}
+
+/// Format a size in bytes for display
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Parse a size such as `512M`, `2G` or `1048576`
+pub fn parse_size(size: &str) -> Result<u64> {
+    let size = size.trim();
+    let digits = size.find(|c: char| !c.is_ascii_digit()).unwrap_or(size.len());
+    let (number, unit) = size.split_at(digits);
+    
+    let number = number.parse::<u64>()
+        .with_context(|| format!("Invalid size: {}", size))?;
+    let unit = unit.trim().trim_end_matches(['B', 'b']).trim_end_matches('i');
+    
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => anyhow::bail!("Invalid size: {}; use a number with an optional K, M, G or T suffix", size),
+    };
+    
+    Ok(number * multiplier)
+}
+
+/// Get when an entry was last used, falling back to its modification time
+fn last_used(cache_dir: &Path, path: &Path, last_use: &BTreeMap<String, u64>) -> Result<SystemTime> {
+    let recorded = path
+        .strip_prefix(cache_dir)
+        .ok()
+        .and_then(|relative| last_use.get(&key(relative)));
+    
+    match recorded {
+        Some(secs) => Ok(UNIX_EPOCH + Duration::from_secs(*secs)),
+        None => Ok(path.metadata()?.modified()?),
+    }
+}
+
+/// Key of an entry in the last-use file
+fn key(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn load_last_use(cache_dir: &Path) -> Result<BTreeMap<String, u64>> {
+    let path = cache_dir.join(LAST_USE_FILE);
+    
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn save_last_use(cache_dir: &Path, last_use: &BTreeMap<String, u64>) -> Result<()> {
+    let path = cache_dir.join(LAST_USE_FILE);
+    
+    std::fs::write(&path, serde_json::to_string_pretty(last_use)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// List the subdirectories of a directory in name order, skipping hidden
+/// ones; a missing directory has none
+fn subdirectories(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let mut dirs = Vec::new();
+    
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        
+        if entry.file_type()?.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            dirs.push(entry.path());
+        }
+    }
+    
+    dirs.sort();
+    
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_cache_entries_and_last_use() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path();
+        
+        // A registry package whose files match its archive
+        let package = cache_dir.join("my-token-1.0.0-beta.1");
+        std::fs::create_dir_all(package.join("src")).unwrap();
+        std::fs::write(package.join("src/lib.qm"), "module token {}\n").unwrap();
+        
+        let mut archive = tar::Builder::new(Vec::new());
+        archive.append_dir_all(".", &package).unwrap();
+        std::fs::write(cache_dir.join("my-token-1.0.0-beta.1.tar"), archive.into_inner().unwrap()).unwrap();
+        
+        // Directories that are not entries
+        std::fs::create_dir_all(cache_dir.join("git/db/lib-0123456789abcdef")).unwrap();
+        std::fs::create_dir_all(cache_dir.join("registries/mirror")).unwrap();
+        
+        let _lock = CacheLock::acquire_blocking(cache_dir).unwrap();
+        
+        let entries = entries(cache_dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "my-token");
+        assert_eq!(entries[0].version, "1.0.0-beta.1");
+        assert!(entries[0].verify().is_ok());
+        
+        record_use(cache_dir, [package.as_path(), Path::new("/elsewhere")]).unwrap();
+        let last_use = load_last_use(cache_dir).unwrap();
+        assert_eq!(last_use.keys().collect::<Vec<_>>(), vec!["my-token-1.0.0-beta.1"]);
+        
+        std::fs::write(package.join("src/lib.qm"), "module changed {}\n").unwrap();
+        assert!(entries[0].verify().is_err());
+        
+        // The database without checkouts is collected with the entry
+        entries[0].remove().unwrap();
+        assert!(remove_unused_databases(cache_dir).is_ok());
+        assert!(!cache_dir.join("git/db/lib-0123456789abcdef").exists());
+        
+        prune_last_use(cache_dir).unwrap();
+        assert!(load_last_use(cache_dir).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 << 30);
+        assert!(parse_size("lots").is_err());
+    }
+    
+    /// Append an entry with a raw path, bypassing the builder's path checks
+    fn append_raw(archive: &mut tar::Builder<Vec<u8>>, path: &str, entry_type: tar::EntryType, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append(&header, data).unwrap();
+    }
+    
+    #[test]
+    fn test_extract_archive_is_atomic_and_validated() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("token-1.0.0");
+        
+        let mut archive = tar::Builder::new(Vec::new());
+        append_raw(&mut archive, "./src/lib.qm", tar::EntryType::Regular, b"module token {}\n");
+        let valid = archive.into_inner().unwrap();
+        
+        extract_archive(&valid, &dest).unwrap();
+        assert_eq!(std::fs::read_to_string(dest.join("src/lib.qm")).unwrap(), "module token {}\n");
+        assert!(!partial_path(&dest).exists());
+        
+        // Unsafe entries are rejected and leave the existing entry alone
+        for (path, entry_type) in [
+            ("../escape.qm", tar::EntryType::Regular),
+            ("/etc/escape.qm", tar::EntryType::Regular),
+            ("src/link.qm", tar::EntryType::Symlink),
+        ] {
+            let mut archive = tar::Builder::new(Vec::new());
+            append_raw(&mut archive, path, entry_type, b"");
+            let error = extract_archive(&archive.into_inner().unwrap(), &dest).unwrap_err();
+            
+            assert!(format!("{:#}", error).contains("entry"), "{:#}", error);
+            assert!(dest.join("src/lib.qm").exists());
+            assert!(!partial_path(&dest).exists());
+            assert!(!temp_dir.path().join("escape.qm").exists());
+        }
+        
+        assert!(extract_archive_with_limit(&valid, &dest, 4).is_err());
+        
+        // A partial entry left by an interrupted extraction is replaced
+        std::fs::create_dir_all(partial_path(&dest).join("stale")).unwrap();
+        extract_archive(&valid, &dest).unwrap();
+        assert!(!partial_path(&dest).exists());
+        assert!(!dest.join("stale").exists());
+    }
+}
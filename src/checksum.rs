@@ -0,0 +1,283 @@
+//! # Checksums
+//!
+//! Blake3 checksums of package archives and source trees.
+//!
+//! A tree checksum covers the relative path and contents of every regular
+//! file, in path order, so the same files hash identically whether they are
+//! read from a tar archive or from an extracted directory.
+//!
+//! Git checkouts and vendored packages additionally carry a checksum file
+//! listing every file, so local edits to them are detected every time they
+//! are loaded.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Component, Path};
+
+/// Name of the checksum file inside a git checkout or vendored package
+pub const CHECKSUM_FILE: &str = ".quantum-checksum.json";
+
+/// Top-level entries that never contribute to a tree checksum; `.git` is
+/// ignored at any depth so git submodules are covered as plain files
+const IGNORED: &[&str] = &[".git", CHECKSUM_FILE];
+
+/// Checksums of a git checkout or vendored package
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageChecksums {
+    /// Checksum recorded for the package in Quantum.lock
+    pub package: String,
+    /// Registry URL or git URL the package came from
+    pub source_url: Option<String>,
+    /// Git commit the package was checked out from
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Blake3 checksum of every file by relative path
+    pub files: BTreeMap<String, String>,
+}
+
+impl PackageChecksums {
+    /// Load the checksum file of a package
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(CHECKSUM_FILE);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+    
+    /// Save the checksum file of a package
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(CHECKSUM_FILE);
+        let content = serde_json::to_string_pretty(self)?;
+        
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+    
+    /// Check that the files of a package are unchanged.
+    ///
+    /// # Arguments
+    /// * `dir` - The package directory
+    pub fn verify(&self, dir: &Path) -> Result<()> {
+        let actual = file_checksums(dir)?;
+        
+        for (file, expected) in &self.files {
+            match actual.get(file) {
+                Some(checksum) if checksum == expected => {}
+                Some(_) => anyhow::bail!("{} was modified", dir.join(file).display()),
+                None => anyhow::bail!("{} is missing", dir.join(file).display()),
+            }
+        }
+        
+        if let Some(file) = actual.keys().find(|file| !self.files.contains_key(*file)) {
+            anyhow::bail!("{} was added", dir.join(file).display());
+        }
+        
+        Ok(())
+    }
+}
+
+/// Compute the checksum of a package archive
+pub fn archive_checksum(archive: &[u8]) -> String {
+    blake3::hash(archive).to_hex().to_string()
+}
+
+/// Compute the tree checksum of a directory.
+///
+/// # Arguments
+/// * `root` - The directory to hash
+///
+/// # Returns
+/// The hex-encoded blake3 checksum of all files below `root`
+pub fn tree_checksum(root: &Path) -> Result<String> {
+    let files = package_files(root)?;
+    let mut hasher = blake3::Hasher::new();
+    
+    for relative in files {
+        let contents = std::fs::read(root.join(&relative))
+            .with_context(|| format!("Failed to read {}", root.join(&relative).display()))?;
+        hash_file(&mut hasher, &relative, &contents);
+    }
+    
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Compute the checksum of every file of a package.
+///
+/// # Returns
+/// The hex-encoded blake3 checksum of each file, by relative path
+pub fn file_checksums(root: &Path) -> Result<BTreeMap<String, String>> {
+    let mut checksums = BTreeMap::new();
+    
+    for relative in package_files(root)? {
+        let contents = std::fs::read(root.join(&relative))
+            .with_context(|| format!("Failed to read {}", root.join(&relative).display()))?;
+        checksums.insert(relative, blake3::hash(&contents).to_hex().to_string());
+    }
+    
+    Ok(checksums)
+}
+
+/// List the files of a package that contribute to its tree checksum.
+///
+/// # Returns
+/// `/`-separated paths relative to `root`, in sorted order
+pub fn package_files(root: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files)?;
+    files.sort();
+    
+    Ok(files)
+}
+
+/// Compute the tree checksum of the files inside a tar archive.
+///
+/// Produces the same checksum as [`tree_checksum`] on the extracted archive.
+pub fn archive_tree_checksum(archive: &[u8]) -> Result<String> {
+    let mut tar = tar::Archive::new(archive);
+    let mut files = Vec::new();
+    
+    for entry in tar.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive entry")?;
+        
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        
+        let Some(relative) = normalize(&entry.path()?) else {
+            continue;
+        };
+        
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push((relative, contents));
+    }
+    
+    files.sort();
+    
+    let mut hasher = blake3::Hasher::new();
+    
+    for (relative, contents) in files {
+        hash_file(&mut hasher, &relative, &contents);
+    }
+    
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Feed one file into a tree hasher
+fn hash_file(hasher: &mut blake3::Hasher, relative: &str, contents: &[u8]) {
+    hasher.update(relative.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(&(contents.len() as u64).to_le_bytes());
+    hasher.update(contents);
+}
+
+/// Turn a path into a `/`-separated relative path, skipping ignored files
+fn normalize(path: &Path) -> Option<String> {
+    let parts = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    
+    match parts.first() {
+        None => None,
+        Some(first) if IGNORED.contains(&first.as_str()) => None,
+        Some(_) if parts.iter().any(|part| part == ".git") => None,
+        Some(_) => Some(parts.join("/")),
+    }
+}
+
+/// Recursively collect the relative paths of all regular files
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        
+        let Some(relative) = normalize(path.strip_prefix(root)?) else {
+            continue;
+        };
+        
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            files.push(relative);
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_archive_and_tree_checksums_agree() {
+        let mut archive = Vec::new();
+        {
+            let mut tar = tar::Builder::new(&mut archive);
+            for (path, contents) in [("Quantum.toml", "[package]\n"), ("src/main.qm", "module a::main {}\n")] {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tar.append_data(&mut header, path, contents.as_bytes()).unwrap();
+            }
+            tar.finish().unwrap();
+        }
+        
+        let temp_dir = TempDir::new().unwrap();
+        tar::Archive::new(archive.as_slice()).unpack(temp_dir.path()).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+        std::fs::write(temp_dir.path().join(".git/HEAD"), "ref").unwrap();
+        
+        let tree = tree_checksum(temp_dir.path()).unwrap();
+        assert_eq!(tree, archive_tree_checksum(&archive).unwrap());
+        
+        std::fs::write(temp_dir.path().join("src/main.qm"), "tampered").unwrap();
+        assert_ne!(tree, tree_checksum(temp_dir.path()).unwrap());
+        
+        // Files shipped under build/ are covered like any other file
+        std::fs::write(temp_dir.path().join("src/main.qm"), "module a::main {}\n").unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("build")).unwrap();
+        std::fs::write(temp_dir.path().join("build/main.qbc"), "bytecode").unwrap();
+        assert_ne!(tree, tree_checksum(temp_dir.path()).unwrap());
+    }
+    
+    #[test]
+    fn test_package_checksums_detect_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+        std::fs::write(temp_dir.path().join("Quantum.toml"), "[package]\n").unwrap();
+        std::fs::write(temp_dir.path().join("src/lib.qm"), "module a::lib {}\n").unwrap();
+        
+        let checksums = PackageChecksums {
+            package: tree_checksum(temp_dir.path()).unwrap(),
+            source_url: None,
+            commit: None,
+            files: file_checksums(temp_dir.path()).unwrap(),
+        };
+        checksums.save(temp_dir.path()).unwrap();
+        
+        let loaded = PackageChecksums::load(temp_dir.path()).unwrap();
+        loaded.verify(temp_dir.path()).unwrap();
+        assert_eq!(loaded.package, tree_checksum(temp_dir.path()).unwrap());
+        
+        std::fs::write(temp_dir.path().join("src/extra.qm"), "").unwrap();
+        let error = loaded.verify(temp_dir.path()).unwrap_err().to_string();
+        assert!(error.ends_with("was added"));
+        
+        std::fs::remove_file(temp_dir.path().join("src/extra.qm")).unwrap();
+        std::fs::write(temp_dir.path().join("src/lib.qm"), "tampered").unwrap();
+        let error = loaded.verify(temp_dir.path()).unwrap_err().to_string();
+        assert!(error.ends_with("was modified"));
+    }
+}
@@ -0,0 +1,287 @@
+//! # Cache Command
+//!
+//! Inspect and clean the package cache shared by every package on the
+//! machine.
+
+use crate::cache::{self, CacheEntry, CacheLock, EntryKind};
+use crate::lockfile::Lockfile;
+use crate::paths;
+use crate::workspace::Workspace;
+use anyhow::Result;
+use clap::Subcommand;
+use colored::Colorize;
+use std::time::{Duration, SystemTime};
+
+/// Subcommands of `quantum cache`
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// List cached packages with their size and last use
+    List,
+    /// Remove cached packages
+    Clean {
+        /// Only remove the versions of this package
+        package: Option<String>,
+    },
+    /// Remove packages that were not used recently
+    ///
+    /// Packages locked by the Quantum.lock of the package in the current
+    /// directory are always kept.
+    Gc {
+        /// Remove packages not used for this many days
+        #[arg(long, default_value_t = 90)]
+        max_age: u64,
+        /// Then remove the least recently used packages until the cache
+        /// fits in this size, e.g. 512M or 2G
+        #[arg(long)]
+        max_size: Option<String>,
+        /// Show what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check cached packages against their recorded checksums
+    Verify,
+}
+
+/// Execute the `quantum cache` command
+///
+/// # Arguments
+/// * `command` - The cache subcommand to run
+pub async fn execute(command: CacheCommand) -> Result<()> {
+    let cache_dir = paths::cache_dir()?;
+    let _lock = CacheLock::acquire(&cache_dir).await?;
+    let entries = cache::entries(&cache_dir)?;
+    
+    match command {
+        CacheCommand::List => list(&entries),
+        CacheCommand::Clean { package } => {
+            let removed = entries
+                .iter()
+                .filter(|entry| package.as_deref().is_none_or(|name| entry.name == name))
+                .collect::<Vec<_>>();
+            
+            if let (Some(package), true) = (&package, removed.is_empty()) {
+                anyhow::bail!("Package {} is not in the cache", package);
+            }
+            
+            let mut freed = remove(&removed, false)?;
+            freed += cache::remove_unused_databases(&cache_dir)?;
+            cache::prune_last_use(&cache_dir)?;
+            
+            println!("{} {} packages, {} total", "Removed".green().bold(), removed.len(), cache::format_size(freed));
+            
+            Ok(())
+        }
+        CacheCommand::Gc { max_age, max_size, dry_run } => {
+            let max_size = max_size.as_deref().map(cache::parse_size).transpose()?;
+            // Outside of a package nothing is pinned
+            let lockfile = Workspace::load_current()
+                .ok()
+                .and_then(|workspace| Lockfile::load_if_exists(workspace.lockfile_path()).ok().flatten());
+            let removed = collect_garbage(&entries, lockfile.as_ref(), max_age, max_size, SystemTime::now());
+            
+            let mut freed = remove(&removed, dry_run)?;
+            
+            if !dry_run {
+                freed += cache::remove_unused_databases(&cache_dir)?;
+                cache::prune_last_use(&cache_dir)?;
+            }
+            
+            let action = if dry_run { "Would remove" } else { "Removed" };
+            println!("{} {} packages, {} total", action.green().bold(), removed.len(), cache::format_size(freed));
+            
+            Ok(())
+        }
+        CacheCommand::Verify => verify(&entries),
+    }
+}
+
+/// Print every cache entry with its size and last use
+fn list(entries: &[CacheEntry]) -> Result<()> {
+    if entries.is_empty() {
+        println!("The package cache is empty");
+        return Ok(());
+    }
+    
+    let now = SystemTime::now();
+    
+    for entry in entries {
+        let version = match entry.kind {
+            EntryKind::Registry => format!("v{}", entry.version),
+            EntryKind::Git => format!("#{}", entry.version),
+        };
+        let days = now.duration_since(entry.last_used).unwrap_or_default().as_secs() / 86400;
+        
+        println!(
+            "{} {} ({}) {}, last used {} days ago",
+            entry.name.bold(),
+            version,
+            entry.source,
+            cache::format_size(entry.size),
+            days
+        );
+    }
+    
+    let total = entries.iter().map(|entry| entry.size).sum();
+    println!();
+    println!("{} packages, {} total", entries.len(), cache::format_size(total));
+    
+    Ok(())
+}
+
+/// Pick the entries to collect.
+///
+/// Entries pinned by the lockfile are kept. Of the rest, those unused for
+/// `max_age` days are collected first, then the least recently used until
+/// the cache fits in `max_size` bytes.
+///
+/// # Arguments
+/// * `entries` - Every cache entry
+/// * `lockfile` - Lockfile of the current package, if any
+/// * `max_age` - Maximum age of unused entries in days
+/// * `max_size` - Maximum size of the cache in bytes
+/// * `now` - The current time
+fn collect_garbage<'a>(
+    entries: &'a [CacheEntry],
+    lockfile: Option<&Lockfile>,
+    max_age: u64,
+    max_size: Option<u64>,
+    now: SystemTime,
+) -> Vec<&'a CacheEntry> {
+    let pinned = |entry: &CacheEntry| {
+        lockfile.iter().flat_map(|lockfile| lockfile.dependencies.values()).any(|locked| match entry.kind {
+            EntryKind::Registry => locked.name == entry.name && locked.version == entry.version,
+            EntryKind::Git => locked.commit.as_deref().is_some_and(|commit| commit.starts_with(&entry.version)),
+        })
+    };
+    
+    let cutoff = now
+        .checked_sub(Duration::from_secs(max_age * 86400))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    
+    let mut candidates = entries.iter().filter(|entry| !pinned(entry)).collect::<Vec<_>>();
+    candidates.sort_by_key(|entry| entry.last_used);
+    
+    let mut size = entries.iter().map(|entry| entry.size).sum::<u64>();
+    let mut removed = Vec::new();
+    
+    for entry in candidates {
+        let over_size = max_size.is_some_and(|max_size| size > max_size);
+        
+        if entry.last_used < cutoff || over_size {
+            size -= entry.size;
+            removed.push(entry);
+        }
+    }
+    
+    removed
+}
+
+/// Remove entries, printing each one
+///
+/// # Arguments
+/// * `entries` - The entries to remove
+/// * `dry_run` - Only print the entries
+///
+/// # Returns
+/// The number of bytes freed
+fn remove(entries: &[&CacheEntry], dry_run: bool) -> Result<u64> {
+    let mut freed = 0;
+    let action = if dry_run { "Would remove" } else { "Removing" };
+    
+    for entry in entries {
+        println!("{} {} {} ({})", action.cyan().bold(), entry.name, entry.version, cache::format_size(entry.size));
+        
+        if !dry_run {
+            entry.remove()?;
+        }
+        
+        freed += entry.size;
+    }
+    
+    Ok(freed)
+}
+
+/// Verify every entry, reporting each one that does not match
+fn verify(entries: &[CacheEntry]) -> Result<()> {
+    let mut failed = 0;
+    
+    for entry in entries {
+        if let Err(error) = entry.verify() {
+            eprintln!("{} {} {}: {:#}", "Corrupted".red().bold(), entry.name, entry.version, error);
+            failed += 1;
+        }
+    }
+    
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} cached packages failed verification; remove them with `quantum cache clean <package>`",
+            failed,
+            entries.len()
+        );
+    }
+    
+    println!("{} {} cached packages", "Verified".green().bold(), entries.len());
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lockfile::LockedDependency;
+    use std::path::PathBuf;
+    
+    fn entry(kind: EntryKind, name: &str, version: &str, size: u64, days: u64, now: SystemTime) -> CacheEntry {
+        CacheEntry {
+            kind,
+            source: "default".to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+            path: PathBuf::from(name),
+            archive: None,
+            size,
+            last_used: now - Duration::from_secs(days * 86400),
+        }
+    }
+    
+    #[test]
+    fn test_collect_garbage() {
+        let now = SystemTime::now();
+        let entries = vec![
+            entry(EntryKind::Registry, "token", "1.0.0", 300, 120, now),
+            entry(EntryKind::Registry, "token", "1.1.0", 300, 100, now),
+            entry(EntryKind::Git, "oracle", "0123456789ab", 200, 200, now),
+            entry(EntryKind::Registry, "math", "0.2.0", 100, 10, now),
+            entry(EntryKind::Registry, "market", "0.1.0", 100, 1, now),
+        ];
+        let names = |removed: Vec<&CacheEntry>| {
+            removed.iter().map(|entry| format!("{}@{}", entry.name, entry.version)).collect::<Vec<_>>()
+        };
+        
+        // Entries unused for longer than the maximum age
+        let removed = collect_garbage(&entries, None, 90, None, now);
+        assert_eq!(names(removed), vec!["oracle@0123456789ab", "token@1.0.0", "token@1.1.0"]);
+        
+        // Then the least recently used until the cache fits
+        let removed = collect_garbage(&entries, None, 365, Some(350), now);
+        assert_eq!(names(removed), vec!["oracle@0123456789ab", "token@1.0.0", "token@1.1.0"]);
+        let removed = collect_garbage(&entries, None, 365, Some(500), now);
+        assert_eq!(names(removed), vec!["oracle@0123456789ab", "token@1.0.0"]);
+        
+        // Locked packages and commits are kept
+        let mut lockfile = Lockfile::new();
+        for (name, version, commit) in [("token", "1.0.0", None), ("oracle", "0.3.0", Some("0123456789abcdef"))] {
+            lockfile.dependencies.insert(name.to_string(), LockedDependency {
+                name: name.to_string(),
+                version: version.to_string(),
+                source: "registry".to_string(),
+                source_url: None,
+                checksum: None,
+                commit: commit.map(str::to_string),
+            });
+        }
+        
+        let removed = collect_garbage(&entries, Some(&lockfile), 90, Some(0), now);
+        assert_eq!(names(removed), vec!["token@1.1.0", "math@0.2.0", "market@0.1.0"]);
+    }
+}
@@ -6,3 +6,8 @@ pub mod new;
 pub mod build;
 pub mod publish;
 pub mod test;
+pub mod tree;
+pub mod update;
+pub mod vendor;
+pub mod yank;
+pub mod cache;
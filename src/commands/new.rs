@@ -57,12 +57,13 @@ pub async fn execute(name: &str, here: bool) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::CurrentDir;
     use tempfile::TempDir;
     
     #[tokio::test]
     async fn test_new_command() {
         let temp_dir = TempDir::new().unwrap();
-        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _current_dir = CurrentDir::change(temp_dir.path());
         
         execute("test_package", false).await.unwrap();
         
@@ -75,7 +76,7 @@ mod tests {
     #[tokio::test]
     async fn test_new_command_here() {
         let temp_dir = TempDir::new().unwrap();
-        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let _current_dir = CurrentDir::change(temp_dir.path());
         
         execute("test_package", true).await.unwrap();
         
@@ -2,18 +2,51 @@
 //!
 //! Publish a Quantum package to the registry.
 
+use crate::config::Config;
+use crate::dependency::ResolveOptions;
+use crate::features::FeatureSelection;
 use crate::package::Package;
 use crate::registry::Registry;
+use crate::workspace::{PackageSelection, Workspace};
 use anyhow::{Context, Result};
 use colored::Colorize;
 use dialoguer::Confirm;
 
 /// Execute the `quantum publish` command
-pub async fn execute(skip_confirm: bool, registry_url: Option<&str>) -> Result<()> {
-    // Load package
-    let package = Package::load_current()
+///
+/// # Arguments
+/// * `skip_confirm` - Do not ask for confirmation
+/// * `registry` - Registry name from the `[registries]` config, or a registry URL
+/// * `options` - How Quantum.lock may be used and updated
+/// * `selection` - Workspace members to publish
+pub async fn execute(
+    skip_confirm: bool,
+    registry: Option<&str>,
+    options: ResolveOptions,
+    selection: &PackageSelection,
+) -> Result<()> {
+    // Load workspace
+    let workspace = Workspace::load_current()
         .context("Failed to load package. Make sure you're in a Quantum package directory.")?;
     
+    if options.offline {
+        anyhow::bail!("Cannot publish while offline");
+    }
+    
+    for package in workspace.selected(selection)? {
+        publish_package(package, skip_confirm, registry, options).await?;
+    }
+    
+    Ok(())
+}
+
+/// Validate, build and upload a single package
+async fn publish_package(
+    package: &Package,
+    skip_confirm: bool,
+    registry: Option<&str>,
+    options: ResolveOptions,
+) -> Result<()> {
     println!("{} {} v{}", 
         "Publishing".green().bold(), 
         package.name().bold(), 
@@ -21,7 +54,7 @@ pub async fn execute(skip_confirm: bool, registry_url: Option<&str>) -> Result<(
     );
     
     // Validate package before publishing
-    validate_package(&package)?;
+    validate_package(package)?;
     
     // Confirm publication
     if !skip_confirm {
@@ -41,20 +74,26 @@ pub async fn execute(skip_confirm: bool, registry_url: Option<&str>) -> Result<(
     }
     
     // Connect to registry
+    let config = Config::load()?;
+    let registry_url = registry.map(|name| config.registry_url(name).unwrap_or(name));
     let registry = Registry::new(registry_url)?;
     
     println!("Connecting to registry...");
     
     // Build package before publishing
     println!("Building package...");
-    crate::commands::build::execute(true, None).await?;
+    let selection = PackageSelection {
+        packages: vec![package.name().to_string()],
+        workspace: false,
+    };
+    crate::commands::build::execute(true, None, options, false, &selection, &FeatureSelection::default(), false).await?;
     
     // Package and upload
     println!("Packaging...");
-    let package_data = create_package_archive(&package)?;
+    let package_data = create_package_archive(package)?;
     
     println!("Uploading to registry...");
-    registry.publish(&package, package_data).await?;
+    registry.publish(package, package_data).await?;
     
     println!();
     println!("{} Package published successfully!", "✓".green().bold());
@@ -75,6 +114,9 @@ fn validate_package(package: &Package) -> Result<()> {
         anyhow::bail!("Package license is required for publishing. Add 'license' to Quantum.toml");
     }
     
+    // Check dependencies can be found without local paths
+    package.manifest.for_publish()?;
+    
     // Check source files exist
     let source_files = package.source_files()?;
     if source_files.is_empty() {
@@ -89,7 +131,7 @@ fn validate_package(package: &Package) -> Result<()> {
 /// Create package archive for upload to registry.
 ///
 /// Packages the Quantum module into a tar archive containing:
-/// - Manifest file (Quantum.toml)
+/// - Manifest file (Quantum.toml), without local paths
 /// - Source code files
 /// - Documentation
 /// - Metadata
@@ -104,7 +146,7 @@ fn create_package_archive(package: &Package) -> Result<Vec<u8>> {
     let mut tar = tar::Builder::new(&mut archive);
     
     // Add manifest
-    let manifest_content = toml::to_string_pretty(&package.manifest)?;
+    let manifest_content = toml::to_string_pretty(&package.manifest.for_publish()?)?;
     let mut header = tar::Header::new_gnu();
     header.set_size(manifest_content.len() as u64);
     header.set_mode(0o644);
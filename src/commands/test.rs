@@ -2,42 +2,64 @@
 //!
 //! Run tests for a Quantum package.
 
+use crate::dependency::ResolveOptions;
+use crate::features::FeatureSelection;
 use crate::package::Package;
+use crate::workspace::{PackageSelection, Workspace};
 use anyhow::{Context, Result};
 use colored::Colorize;
 
 /// Execute the `quantum test` command
-pub async fn execute(filter: Option<&str>) -> Result<()> {
-    // Load package
-    let package = Package::load_current()
+///
+/// # Arguments
+/// * `filter` - Only run tests whose name contains this string
+/// * `options` - How Quantum.lock may be used and updated
+/// * `selection` - Workspace members to test
+/// * `features` - Features to enable in the tested packages
+pub async fn execute(
+    filter: Option<&str>,
+    options: ResolveOptions,
+    selection: &PackageSelection,
+    features: &FeatureSelection,
+) -> Result<()> {
+    // Load workspace
+    let workspace = Workspace::load_current()
         .context("Failed to load package. Make sure you're in a Quantum package directory.")?;
     
-    println!("{} {} v{}", 
-        "Testing".green().bold(), 
-        package.name().bold(), 
-        package.version()
-    );
+    let packages = workspace.selected(selection)?;
     
     if let Some(filter_str) = filter {
         println!("Filter: {}", filter_str);
     }
     
-    // Build package first
+    // Build packages first
     println!();
     println!("Building package...");
-    crate::commands::build::execute(false, None).await?;
-    
-    // Find and run tests
-    println!();
-    println!("Running tests...");
+    crate::commands::build::execute(false, None, options, true, selection, features, false).await?;
     
-    let test_results = run_tests(&package, filter)?;
+    let mut failed = false;
     
-    // Print results
-    println!();
-    print_test_results(&test_results);
+    for package in packages {
+        println!();
+        println!("{} {} v{}", 
+            "Testing".green().bold(), 
+            package.name().bold(), 
+            package.version()
+        );
+        
+        // Find and run tests
+        println!("Running tests...");
+        
+        let test_results = run_tests(package, filter)?;
+        
+        // Print results
+        println!();
+        print_test_results(&test_results);
+        
+        failed |= test_results.failed > 0;
+    }
     
-    if test_results.failed > 0 {
+    if failed {
         anyhow::bail!("Tests failed");
     }
     
@@ -0,0 +1,444 @@
+//! # Tree Command
+//!
+//! Display the dependency graph of a Quantum package.
+//!
+//! Dev-dependencies of the package are shown in their own section. They
+//! are left out of the inverted graph, so `--invert` and `--duplicates`
+//! only follow the dependencies a build uses.
+
+use crate::dependency::{DependencyResolver, DependencySource, ResolvedDependencies};
+use crate::lockfile::Lockfile;
+use crate::package::Package;
+use crate::workspace::Workspace;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::{self, Write};
+
+/// Output format for `quantum tree`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TreeFormat {
+    /// Indented tree
+    Text,
+    /// JSON nodes and edges
+    Json,
+    /// Graphviz DOT graph
+    Dot,
+}
+
+/// Execute the `quantum tree` command
+///
+/// # Arguments
+/// * `invert` - Show the packages that depend on this package instead
+/// * `depth` - Maximum depth to display
+/// * `duplicates` - Show only packages required by more than one dependent
+/// * `format` - Output format
+pub async fn execute(
+    invert: Option<&str>,
+    depth: Option<usize>,
+    duplicates: bool,
+    format: TreeFormat,
+) -> Result<()> {
+    // Load package
+    let workspace = Workspace::load_current()
+        .context("Failed to load package. Make sure you're in a Quantum package directory.")?;
+    
+    let Some(package) = workspace.current() else {
+        anyhow::bail!("Run `quantum tree` in the directory of a workspace member");
+    };
+    
+    let lockfile = Lockfile::load_if_exists(workspace.lockfile_path())?;
+    let resolver = DependencyResolver::new(None)?.with_lockfile(lockfile);
+    let resolved = resolver.resolve(&workspace).await?;
+    
+    let graph = Graph {
+        package,
+        resolved: &resolved,
+        invert: invert.is_some() || duplicates,
+    };
+    
+    let starts = graph.starts(invert, duplicates)?;
+    print!("{}", graph.render(&starts, depth, format)?);
+    
+    Ok(())
+}
+
+/// A node in the displayed graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    /// The package being inspected
+    Root,
+    /// A resolved dependency
+    Package(String),
+}
+
+/// View over the resolved dependency graph, optionally inverted
+struct Graph<'a> {
+    package: &'a Package,
+    resolved: &'a ResolvedDependencies,
+    invert: bool,
+}
+
+impl Graph<'_> {
+    /// Get the nodes the displayed graph starts from
+    ///
+    /// # Arguments
+    /// * `invert` - Start from this package instead of the root
+    /// * `duplicates` - Start from every package with more than one dependent
+    fn starts(&self, invert: Option<&str>, duplicates: bool) -> Result<Vec<Node>> {
+        if duplicates {
+            return Ok(self.resolved.all()
+                .keys()
+                .filter(|name| self.children(&Node::Package(name.to_string())).len() > 1)
+                .map(|name| Node::Package(name.clone()))
+                .collect());
+        }
+        
+        match invert {
+            Some(name) if self.resolved.get(name).is_none() => {
+                anyhow::bail!("Package {} is not in the dependency graph", name);
+            }
+            Some(name) => Ok(vec![Node::Package(name.to_string())]),
+            None => Ok(vec![Node::Root]),
+        }
+    }
+    
+    /// Render the graph reachable from the start nodes in an output format
+    fn render(&self, starts: &[Node], depth: Option<usize>, format: TreeFormat) -> Result<String> {
+        let mut output = String::new();
+        
+        match format {
+            TreeFormat::Text => {
+                for (i, start) in starts.iter().enumerate() {
+                    if i > 0 {
+                        output.push('\n');
+                    }
+                    
+                    writeln!(output, "{}", self.label(start))?;
+                    self.write_children(&mut output, start, "", 0, depth, &mut BTreeSet::new())?;
+                }
+            }
+            TreeFormat::Json => {
+                let (nodes, edges) = self.collect(starts, depth);
+                
+                let nodes = nodes.iter()
+                    .map(|node| self.json_node(node))
+                    .collect::<Vec<_>>();
+                let edges = edges.iter()
+                    .map(|(from, to, dev)| serde_json::json!({
+                        "from": self.id(from),
+                        "to": self.id(to),
+                        "kind": if *dev { "dev" } else { "normal" },
+                    }))
+                    .collect::<Vec<_>>();
+                
+                let json = serde_json::json!({ "nodes": nodes, "edges": edges });
+                writeln!(output, "{}", serde_json::to_string_pretty(&json)?)?;
+            }
+            TreeFormat::Dot => {
+                let (nodes, edges) = self.collect(starts, depth);
+                
+                writeln!(output, "digraph dependencies {{")?;
+                for node in &nodes {
+                    writeln!(output, "    \"{}\" [label=\"{}\"];", dot_escape(&self.id(node)), dot_escape(&self.label(node)))?;
+                }
+                for (from, to, dev) in &edges {
+                    let style = if *dev { " [style=dashed]" } else { "" };
+                    writeln!(output, "    \"{}\" -> \"{}\"{};", dot_escape(&self.id(from)), dot_escape(&self.id(to)), style)?;
+                }
+                writeln!(output, "}}")?;
+            }
+        }
+        
+        Ok(output)
+    }
+    
+    /// Get the children of a node: its dependencies, or its dependents when inverted
+    fn children(&self, node: &Node) -> Vec<Node> {
+        match node {
+            Node::Root if self.invert => Vec::new(),
+            Node::Root => self.resolved.roots(self.package.name())
+                .iter()
+                .map(|name| Node::Package(name.clone()))
+                .collect(),
+            Node::Package(name) if self.invert => {
+                let mut dependents = self.resolved.dependents_of(name)
+                    .map(|dependent| Node::Package(dependent.to_string()))
+                    .collect::<Vec<_>>();
+                
+                if self.resolved.roots(self.package.name()).contains(name) {
+                    dependents.insert(0, Node::Root);
+                }
+                
+                dependents
+            }
+            Node::Package(name) => self.resolved.dependencies_of(name)
+                .map(|dep| Node::Package(dep.to_string()))
+                .collect(),
+        }
+    }
+    
+    /// Get the dev-dependencies of a node; only the root has any, and only
+    /// when not inverted
+    fn dev_children(&self, node: &Node) -> Vec<Node> {
+        match node {
+            Node::Root if !self.invert => self.resolved.dev_roots(self.package.name())
+                .iter()
+                .map(|name| Node::Package(name.clone()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Get a stable identifier for a node
+    fn id(&self, node: &Node) -> String {
+        match node {
+            Node::Root => self.package.name().to_string(),
+            Node::Package(name) => name.clone(),
+        }
+    }
+    
+    /// Get the display label of a node
+    fn label(&self, node: &Node) -> String {
+        match node {
+            Node::Root => format!("{} v{}", self.package.name(), self.package.version()),
+            Node::Package(name) => match self.resolved.get(name) {
+                Some(info) => match (&info.source, &info.source_url) {
+                    (DependencySource::Registry, _) | (_, None) => format!("{} v{}", name, info.version),
+                    (_, Some(url)) => format!("{} v{} ({})", name, info.version, url),
+                },
+                None => name.clone(),
+            },
+        }
+    }
+    
+    /// Describe a node as a JSON object
+    fn json_node(&self, node: &Node) -> serde_json::Value {
+        match node {
+            Node::Root => serde_json::json!({
+                "name": self.package.name(),
+                "version": self.package.version(),
+                "root": true,
+            }),
+            Node::Package(name) => {
+                let info = self.resolved.get(name);
+                serde_json::json!({
+                    "name": name,
+                    "version": info.map(|info| info.version.as_str()),
+                    "source": info.map(|info| info.source.as_str()),
+                    "source_url": info.and_then(|info| info.source_url.as_deref()),
+                })
+            }
+        }
+    }
+    
+    /// Write the children of a node as an indented tree.
+    ///
+    /// Dev-dependencies follow under a `[dev-dependencies]` heading. Subtrees
+    /// that were already written are marked with `(*)` instead of being
+    /// expanded again.
+    fn write_children(
+        &self,
+        output: &mut String,
+        node: &Node,
+        prefix: &str,
+        depth: usize,
+        max_depth: Option<usize>,
+        seen: &mut BTreeSet<Node>,
+    ) -> fmt::Result {
+        if max_depth.is_some_and(|max| depth >= max) {
+            return Ok(());
+        }
+        
+        self.write_nodes(output, &self.children(node), prefix, depth, max_depth, seen)?;
+        
+        let dev_children = self.dev_children(node);
+        
+        if !dev_children.is_empty() {
+            writeln!(output, "{}[dev-dependencies]", prefix)?;
+            self.write_nodes(output, &dev_children, prefix, depth, max_depth, seen)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Write sibling nodes and their subtrees
+    fn write_nodes(
+        &self,
+        output: &mut String,
+        children: &[Node],
+        prefix: &str,
+        depth: usize,
+        max_depth: Option<usize>,
+        seen: &mut BTreeSet<Node>,
+    ) -> fmt::Result {
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            let repeated = !seen.insert(child.clone()) && !self.children(child).is_empty();
+            
+            writeln!(output, "{}{}{}{}", prefix, branch, self.label(child), if repeated { " (*)" } else { "" })?;
+            
+            if !repeated {
+                let prefix = format!("{}{}", prefix, indent);
+                self.write_children(output, child, &prefix, depth + 1, max_depth, seen)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Collect the nodes and dependency edges reachable from the start nodes.
+    ///
+    /// Nodes are visited breadth-first, so each one is expanded at its
+    /// shallowest depth. Edges always point from dependent to dependency,
+    /// even when inverted, and are marked when they lead to a dev-dependency.
+    fn collect(&self, starts: &[Node], max_depth: Option<usize>) -> (BTreeSet<Node>, BTreeSet<(Node, Node, bool)>) {
+        let mut nodes = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        let mut queue = starts.iter().map(|node| (node.clone(), 0)).collect::<VecDeque<_>>();
+        
+        while let Some((node, depth)) = queue.pop_front() {
+            if !nodes.insert(node.clone()) || max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            
+            let children = self.children(&node).into_iter().map(|child| (child, false));
+            let dev_children = self.dev_children(&node).into_iter().map(|child| (child, true));
+            
+            for (child, dev) in children.chain(dev_children) {
+                if self.invert {
+                    edges.insert((child.clone(), node.clone(), dev));
+                } else {
+                    edges.insert((node.clone(), child.clone(), dev));
+                }
+                
+                queue.push_back((child, depth + 1));
+            }
+        }
+        
+        (nodes, edges)
+    }
+}
+
+/// Escape a string for a quoted Graphviz identifier
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{isolated_resolver, write_package};
+    use tempfile::TempDir;
+    
+    #[tokio::test]
+    async fn test_tree_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        // The quote in the directory name ends up in the label of token
+        write_package(&root.join("app"), "app", "[dependencies]\ntoken = { path = \"../to\\\"ken\" }\nmarket = { path = \"../market\" }\n");
+        write_package(&root.join("market"), "market", "[dependencies]\ntoken = { path = \"../to\\\"ken\" }\n");
+        write_package(&root.join("to\"ken"), "token", "");
+        
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let resolved = isolated_resolver(&root.join("cache")).resolve(&workspace).await.unwrap();
+        let graph = Graph { package: &workspace.members[0], resolved: &resolved, invert: false };
+        
+        let output = graph.render(&graph.starts(None, false).unwrap(), None, TreeFormat::Text).unwrap();
+        let lines = output.lines().map(|line| line.split(" (").next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(lines, vec!["app v0.1.0", "├── market v0.1.0", "│   └── token v0.1.0", "└── token v0.1.0"]);
+        
+        let output = graph.render(&[Node::Root], Some(1), TreeFormat::Json).unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 3);
+        assert_eq!(json["edges"].as_array().unwrap().len(), 2);
+        
+        let output = graph.render(&[Node::Root], None, TreeFormat::Dot).unwrap();
+        assert!(output.contains("    \"market\" -> \"token\";"), "{}", output);
+        assert!(output.contains("to\\\"ken"), "{}", output);
+        assert!(!output.contains("to\"ken"), "{}", output);
+        
+        // Inverted, edges still point from dependent to dependency
+        let graph = Graph { invert: true, ..graph };
+        let starts = graph.starts(Some("token"), false).unwrap();
+        let output = graph.render(&starts, None, TreeFormat::Text).unwrap();
+        let lines = output.lines().map(|line| line.split(" (").next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(lines, vec!["token v0.1.0", "├── app v0.1.0", "└── market v0.1.0", "    └── app v0.1.0"]);
+        
+        let output = graph.render(&starts, None, TreeFormat::Dot).unwrap();
+        assert!(output.contains("    \"app\" -> \"market\";"), "{}", output);
+        assert!(graph.starts(Some("missing"), false).is_err());
+        
+        // Only token is required by more than one dependent
+        assert_eq!(graph.starts(None, true).unwrap(), vec![Node::Package("token".to_string())]);
+    }
+    
+    #[tokio::test]
+    async fn test_tree_depth_expands_shallowest_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        // token is reached at depth 1 from app and at depth 2 through zeta
+        write_package(&root.join("app"), "app", "[dependencies]\ntoken = { path = \"../token\" }\nzeta = { path = \"../zeta\" }\n");
+        write_package(&root.join("zeta"), "zeta", "[dependencies]\ntoken = { path = \"../token\" }\n");
+        write_package(&root.join("token"), "token", "[dependencies]\nmath = { path = \"../math\" }\n");
+        write_package(&root.join("math"), "math", "");
+        
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let resolved = isolated_resolver(&root.join("cache")).resolve(&workspace).await.unwrap();
+        let graph = Graph { package: &workspace.members[0], resolved: &resolved, invert: false };
+        
+        let output = graph.render(&[Node::Root], Some(2), TreeFormat::Json).unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 4);
+        assert_eq!(json["edges"].as_array().unwrap().len(), 4);
+        
+        let output = graph.render(&[Node::Root], Some(2), TreeFormat::Dot).unwrap();
+        assert!(output.contains("    \"token\" -> \"math\";"), "{}", output);
+        assert!(output.contains("    \"zeta\" -> \"token\";"), "{}", output);
+    }
+    
+    #[tokio::test]
+    async fn test_tree_marks_dev_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        // token is a dev-dependency of app and a dependency of market
+        write_package(&root.join("app"), "app", "[dependencies]\nmarket = { path = \"../market\" }\n\n[dev-dependencies]\ntoken = { path = \"../token\" }\n");
+        write_package(&root.join("market"), "market", "[dependencies]\ntoken = { path = \"../token\" }\n");
+        write_package(&root.join("token"), "token", "");
+        
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let resolved = isolated_resolver(&root.join("cache")).resolve(&workspace).await.unwrap();
+        let graph = Graph { package: &workspace.members[0], resolved: &resolved, invert: false };
+        
+        let output = graph.render(&[Node::Root], None, TreeFormat::Text).unwrap();
+        let lines = output.lines().map(|line| line.split(" (").next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec!["app v0.1.0", "└── market v0.1.0", "    └── token v0.1.0", "[dev-dependencies]", "└── token v0.1.0"]
+        );
+        
+        let output = graph.render(&[Node::Root], None, TreeFormat::Json).unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        let dev_edges = json["edges"].as_array().unwrap()
+            .iter()
+            .filter(|edge| edge["kind"] == "dev")
+            .map(|edge| (edge["from"].as_str().unwrap(), edge["to"].as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(dev_edges, vec![("app", "token")]);
+        
+        let output = graph.render(&[Node::Root], None, TreeFormat::Dot).unwrap();
+        assert!(output.contains("    \"app\" -> \"token\" [style=dashed];"), "{}", output);
+        
+        // The dev edge is not followed when inverted, so token has one dependent
+        let graph = Graph { invert: true, ..graph };
+        let starts = graph.starts(Some("token"), false).unwrap();
+        let output = graph.render(&starts, None, TreeFormat::Text).unwrap();
+        let lines = output.lines().map(|line| line.split(" (").next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(lines, vec!["token v0.1.0", "└── market v0.1.0", "    └── app v0.1.0"]);
+        assert!(graph.starts(None, true).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+//! # Update Command
+//!
+//! Update dependencies recorded in Quantum.lock.
+
+use crate::dependency::DependencyResolver;
+use crate::lockfile::{LockChange, LockedDependency, Lockfile};
+use crate::version;
+use crate::workspace::Workspace;
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+/// Execute the `quantum update` command.
+///
+/// Moves the named packages, or every package when none are named, to the
+/// newest versions compatible with the manifest while keeping the rest of
+/// Quantum.lock as it is. Git dependencies that follow a branch are fetched
+/// again. Named packages must already be in Quantum.lock.
+///
+/// # Arguments
+/// * `packages` - Packages to update; empty updates everything
+/// * `precise` - Exact version to update a single package to
+/// * `dry_run` - Print the changes without writing Quantum.lock
+pub async fn execute(packages: &[String], precise: Option<&str>, dry_run: bool) -> Result<()> {
+    // Load package
+    let workspace = Workspace::load_current()
+        .context("Failed to load package. Make sure you're in a Quantum package directory.")?;
+    
+    if precise.is_some() && packages.len() != 1 {
+        anyhow::bail!("--precise requires exactly one package to update");
+    }
+    
+    let lockfile_path = workspace.lockfile_path();
+    let existing = Lockfile::load_if_exists(&lockfile_path)?;
+    
+    println!("{} {}", "Updating".green().bold(), lockfile_path.display());
+    
+    // Unlock the packages being updated
+    let locked = match &existing {
+        None if !packages.is_empty() => anyhow::bail!(
+            "Cannot update {} without Quantum.lock; run `quantum update` without package names to create it",
+            packages.join(", ")
+        ),
+        Some(lockfile) if !packages.is_empty() => {
+            let mut lockfile = lockfile.clone();
+            
+            for name in packages {
+                if lockfile.dependencies.remove(name).is_none() {
+                    anyhow::bail!("Package {} is not in Quantum.lock", name);
+                }
+            }
+            
+            Some(lockfile)
+        }
+        _ => None,
+    };
+    
+    let mut resolver = DependencyResolver::new(None)?.with_lockfile(locked);
+    
+    if let Some(precise) = precise {
+        let precise = version::parse_version(precise)?;
+        resolver = resolver.with_precise(&packages[0], precise);
+    }
+    
+    let resolved = resolver.resolve(&workspace).await?;
+    let lockfile = Lockfile::from_resolved(&resolved);
+    
+    let changes = existing.unwrap_or_default().diff(&lockfile);
+    
+    if changes.is_empty() {
+        println!("Quantum.lock is already up to date");
+    }
+    
+    for change in &changes {
+        print_change(change);
+    }
+    
+    if dry_run {
+        println!();
+        println!("{} not updating Quantum.lock due to --dry-run", "warning:".yellow().bold());
+        return Ok(());
+    }
+    
+    lockfile.save(&lockfile_path)?;
+    
+    Ok(())
+}
+
+/// Print a single lockfile change
+fn print_change(change: &LockChange) {
+    match change {
+        LockChange::Added(dep) => {
+            println!("  {} {} {}", "Adding".green().bold(), dep.name, describe(dep));
+        }
+        LockChange::Removed(dep) => {
+            println!("  {} {} {}", "Removing".red().bold(), dep.name, describe(dep));
+        }
+        LockChange::Updated { old, new } => {
+            println!("  {} {} {} -> {}", "Updating".green().bold(), new.name, describe(old), describe(new));
+        }
+    }
+}
+
+/// Describe a locked version, including the git commit if there is one
+fn describe(dep: &LockedDependency) -> String {
+    match &dep.commit {
+        Some(commit) => format!("v{} ({})", dep.version, &commit[..commit.len().min(8)]),
+        None => format!("v{}", dep.version),
+    }
+}
@@ -0,0 +1,256 @@
+//! # Vendor Command
+//!
+//! Copy all registry and git dependencies into the package so it can be
+//! built without network access.
+
+use crate::checksum::{self, PackageChecksums, CHECKSUM_FILE};
+use crate::config::{self, SourceConfig, DEFAULT_REGISTRY_SOURCE};
+use crate::dependency::{DependencyResolver, DependencySource, ResolveOptions};
+use crate::workspace::Workspace;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Name of the source that points to the vendor directory
+const VENDORED_SOURCE: &str = "vendored-sources";
+
+/// Execute the `quantum vendor` command.
+///
+/// Copies every resolved registry and git dependency into `dir/<name>`,
+/// together with a checksum file, and records a source replacement in
+/// .quantum/config.toml so later builds use the vendored copies.
+///
+/// # Arguments
+/// * `dir` - Vendor directory relative to the workspace root, defaults to `vendor`
+/// * `options` - How Quantum.lock may be used and updated
+pub async fn execute(dir: Option<&str>, options: ResolveOptions) -> Result<()> {
+    // Load package
+    let workspace = Workspace::load_current()
+        .context("Failed to load package. Make sure you're in a Quantum package directory.")?;
+    
+    // Resolve from the original sources, not from a previous vendor directory
+    let resolver = DependencyResolver::new(None)?.without_vendored_sources();
+    
+    vendor(&workspace, resolver, dir.unwrap_or("vendor"), options).await
+}
+
+/// Vendor the dependencies of a workspace resolved by a resolver
+///
+/// # Arguments
+/// * `workspace` - The workspace whose dependencies are vendored
+/// * `resolver` - Resolver for the original sources of the dependencies
+/// * `dir` - Vendor directory relative to the workspace root
+/// * `options` - How Quantum.lock may be used and updated
+async fn vendor(workspace: &Workspace, resolver: DependencyResolver, dir: &str, options: ResolveOptions) -> Result<()> {
+    let vendor_dir = workspace.root.join(dir);
+    let resolved = workspace.resolve(resolver, options).await?;
+    
+    fs::create_dir_all(&vendor_dir)
+        .with_context(|| format!("Failed to create {}", vendor_dir.display()))?;
+    
+    let vendored = resolved.all()
+        .iter()
+        .filter(|(_, info)| !matches!(info.source, DependencySource::Path))
+        .collect::<BTreeMap<_, _>>();
+    
+    remove_stale_packages(&vendor_dir, |name| vendored.keys().any(|v| *v == name))?;
+    
+    // Copy each package with its checksums
+    let mut sources = BTreeMap::new();
+    
+    for (name, info) in &vendored {
+        let dest = vendor_dir.join(name);
+        
+        if dest.exists() {
+            fs::remove_dir_all(&dest)
+                .with_context(|| format!("Failed to remove {}", dest.display()))?;
+        }
+        
+        copy_package(&info.path, &dest)?;
+        
+        let checksums = PackageChecksums {
+            package: info.checksum.clone(),
+            source_url: info.source_url.clone(),
+            commit: info.commit.clone(),
+            files: checksum::file_checksums(&dest)?,
+        };
+        checksums.save(&dest)?;
+        
+        let (source, git) = match (&info.source, &info.source_url) {
+            (DependencySource::Git, Some(url)) => (config::git_source(url), Some(url.clone())),
+            _ => (info.registry.clone().unwrap_or_else(|| DEFAULT_REGISTRY_SOURCE.to_string()), None),
+        };
+        
+        sources.insert(source, SourceConfig {
+            replace_with: Some(VENDORED_SOURCE.to_string()),
+            git,
+            ..Default::default()
+        });
+        
+        println!("  {} {} v{}", "Vendoring".green().bold(), name, info.version);
+    }
+    
+    sources.insert(VENDORED_SOURCE.to_string(), SourceConfig {
+        directory: Some(dir.into()),
+        ..Default::default()
+    });
+    
+    let mut snippet = toml::Table::new();
+    snippet.insert("source".to_string(), toml::Value::try_from(&sources)?);
+    
+    let config_path = workspace.root.join(".quantum").join("config.toml");
+    write_sources(&config_path, &snippet)?;
+    
+    println!();
+    println!("{} Vendored {} package(s) into {}",
+        "✓".green().bold(),
+        vendored.len(),
+        vendor_dir.display()
+    );
+    println!("Source replacement written to {}:", config_path.display());
+    println!();
+    print!("{}", toml::to_string_pretty(&snippet)?);
+    
+    Ok(())
+}
+
+/// Remove vendored packages that are no longer part of the dependency graph.
+///
+/// Only directories containing a checksum file are touched, so anything
+/// else kept in the vendor directory is left alone.
+fn remove_stale_packages(vendor_dir: &Path, keep: impl Fn(&str) -> bool) -> Result<()> {
+    for entry in fs::read_dir(vendor_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        
+        if path.join(CHECKSUM_FILE).exists() && !keep(&name) {
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Copy the files of a package, skipping version control and build output
+fn copy_package(src: &Path, dest: &Path) -> Result<()> {
+    for relative in checksum::package_files(src)? {
+        let target = dest.join(&relative);
+        
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        fs::copy(src.join(&relative), &target)
+            .with_context(|| format!("Failed to copy {}", src.join(&relative).display()))?;
+    }
+    
+    Ok(())
+}
+
+/// Merge a `[source]` snippet into a config file, keeping its other settings
+fn write_sources(path: &Path, snippet: &toml::Table) -> Result<()> {
+    let mut config = if path.exists() {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        content.parse::<toml::Table>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        toml::Table::new()
+    };
+    
+    let table = config
+        .entry("source")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .with_context(|| format!("`source` in {} is not a table", path.display()))?;
+    
+    if let Some(sources) = snippet.get("source").and_then(toml::Value::as_table) {
+        table.extend(sources.clone());
+    }
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    fs::write(path, toml::to_string_pretty(&config)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cache_package, git, isolated_resolver, package_archive, write_package};
+    use tempfile::TempDir;
+    
+    #[tokio::test]
+    async fn test_vendor_registry_and_git_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        let work = temp_dir.path().join("lib");
+        let resolver = || isolated_resolver(&temp_dir.path().join("cache"));
+        
+        // A cached registry package and a git repository
+        cache_package(&resolver(), "a", "0.1.0");
+        
+        write_package(&work, "lib", "");
+        std::fs::write(work.join("src/lib.qm"), "module lib {}\n").unwrap();
+        git(&work, &["init", "--quiet", "-b", "main"]);
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "--quiet", "-m", "lib"]);
+        let commit = git(&work, &["rev-parse", "HEAD"]);
+        let url = work.to_string_lossy().into_owned();
+        
+        // Check out the git dependency, so that vendoring can run offline
+        write_package(&root, "app", &format!("[dependencies]\nlib = {{ git = {:?} }}\n", url));
+        resolver().resolve(&Workspace::load(&root).unwrap()).await.unwrap();
+        
+        write_package(&root, "app", &format!("[dependencies]\na = \"^0.1\"\nlib = {{ git = {:?} }}\n", url));
+        std::fs::create_dir_all(root.join(".quantum")).unwrap();
+        std::fs::write(root.join(".quantum/config.toml"), "[source.mirror]\ndirectory = \"mirror\"\n").unwrap();
+        
+        let workspace = Workspace::load(&root).unwrap();
+        let options = ResolveOptions { offline: true, ..Default::default() };
+        vendor(&workspace, resolver(), "vendor", options).await.unwrap();
+        
+        // The files of both packages are copied with their checksums
+        let vendor_dir = root.join("vendor");
+        assert!(vendor_dir.join("a/Quantum.toml").exists());
+        assert_eq!(std::fs::read_to_string(vendor_dir.join("lib/src/lib.qm")).unwrap(), "module lib {}\n");
+        assert!(!vendor_dir.join("lib/.git").exists());
+        
+        let a = PackageChecksums::load(&vendor_dir.join("a")).unwrap();
+        assert_eq!(a.package, checksum::archive_checksum(&package_archive("a", "0.1.0")));
+        assert_eq!(a.files.keys().collect::<Vec<_>>(), vec!["Quantum.toml"]);
+        a.verify(&vendor_dir.join("a")).unwrap();
+        
+        let lib = PackageChecksums::load(&vendor_dir.join("lib")).unwrap();
+        assert_eq!(lib.source_url.as_deref(), Some(url.as_str()));
+        assert_eq!(lib.commit.as_deref(), Some(commit.as_str()));
+        assert_eq!(lib.files.keys().collect::<Vec<_>>(), vec!["Quantum.toml", "src/lib.qm"]);
+        
+        // The source replacement is merged into the existing configuration
+        let config_path = root.join(".quantum/config.toml");
+        let config = std::fs::read_to_string(&config_path).unwrap().parse::<toml::Table>().unwrap();
+        let sources = config["source"].as_table().unwrap();
+        assert_eq!(sources["mirror"]["directory"].as_str(), Some("mirror"));
+        assert_eq!(sources[DEFAULT_REGISTRY_SOURCE]["replace-with"].as_str(), Some(VENDORED_SOURCE));
+        assert_eq!(sources[&config::git_source(&url)]["replace-with"].as_str(), Some(VENDORED_SOURCE));
+        assert_eq!(sources[&config::git_source(&url)]["git"].as_str(), Some(url.as_str()));
+        assert_eq!(sources[VENDORED_SOURCE]["directory"].as_str(), Some("vendor"));
+        
+        // Vendoring again changes nothing
+        let snapshot = |dir: &Path| checksum::file_checksums(dir).unwrap();
+        let (vendored_a, vendored_lib) = (snapshot(&vendor_dir.join("a")), snapshot(&vendor_dir.join("lib")));
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        
+        vendor(&workspace, resolver(), "vendor", options).await.unwrap();
+        
+        assert_eq!(snapshot(&vendor_dir.join("a")), vendored_a);
+        assert_eq!(snapshot(&vendor_dir.join("lib")), vendored_lib);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config);
+    }
+}
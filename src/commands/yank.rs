@@ -0,0 +1,49 @@
+//! # Yank Command
+//!
+//! Remove a published version from new resolutions, or make it available
+//! again.
+
+use crate::config::Config;
+use crate::registry::Registry;
+use crate::version;
+use anyhow::Result;
+use colored::Colorize;
+
+/// Execute the `quantum yank` command
+///
+/// # Arguments
+/// * `spec` - The package version to yank, as `name@version`
+/// * `undo` - Undo a previous yank
+/// * `registry` - Registry name from the `[registries]` config, or a registry URL
+pub async fn execute(spec: &str, undo: bool, registry: Option<&str>) -> Result<()> {
+    let Some((name, version)) = spec.split_once('@') else {
+        anyhow::bail!("Expected a package version as name@version, got {}", spec);
+    };
+    
+    version::parse_version(version)?;
+    
+    let config = Config::load()?;
+    
+    if config.net.offline {
+        anyhow::bail!("Cannot yank while offline");
+    }
+    
+    let registry_url = registry.map(|name| config.registry_url(name).unwrap_or(name));
+    let registry = Registry::new(registry_url)?;
+    
+    let action = if undo { "Unyanking" } else { "Yanking" };
+    println!("{} {} v{}", action.green().bold(), name.bold(), version);
+    
+    registry.yank(name, version, undo).await?;
+    
+    println!();
+    if undo {
+        println!("{} {} v{} can be selected again", "✓".green().bold(), name, version);
+    } else {
+        println!("{} {} v{} was yanked", "✓".green().bold(), name, version);
+        println!("  Packages whose Quantum.lock pins it can still use it");
+    }
+    println!("  Registry: {}", registry.url());
+    
+    Ok(())
+}
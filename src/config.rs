@@ -0,0 +1,223 @@
+//! # CLI Configuration
+//!
+//! User configuration loaded from config.toml in the Quantum home directory
+//! (see [`crate::paths`]), merged with the project configuration in the
+//! nearest .quantum/config.toml above the current directory.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Source name of the official package registry
+pub const DEFAULT_REGISTRY_SOURCE: &str = "quantum-registry";
+
+/// Maximum length of a `replace-with` chain
+const MAX_REPLACEMENTS: usize = 16;
+
+/// Quantum CLI configuration (config.toml)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Network settings
+    #[serde(default)]
+    pub net: NetConfig,
+    /// Named package registries
+    #[serde(default)]
+    pub registries: BTreeMap<String, RegistryConfig>,
+    /// Package sources and their replacements
+    #[serde(default)]
+    pub source: BTreeMap<String, SourceConfig>,
+}
+
+/// A named package registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    /// Registry URL
+    pub url: String,
+}
+
+/// Network configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetConfig {
+    /// Never access the network; use only cached dependencies
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/// A package source that can replace, or be replaced by, another source
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceConfig {
+    /// Name of the source that replaces this one
+    #[serde(default)]
+    pub replace_with: Option<String>,
+    /// Registry URL serving this source, e.g. a mirror
+    #[serde(default)]
+    pub registry: Option<String>,
+    /// Local directory of vendored packages
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+    /// Git repository this source stands for
+    #[serde(default)]
+    pub git: Option<String>,
+}
+
+impl Config {
+    /// Load the user configuration merged with the project configuration.
+    ///
+    /// # Returns
+    /// The merged configuration, or the defaults if no config file exists
+    pub fn load() -> Result<Self> {
+        let user_path = crate::paths::config_file()?;
+        let mut config = Self::load_file(&user_path)?.unwrap_or_default();
+        
+        // Without a current directory there is no project configuration
+        let project_path = std::env::current_dir().ok().and_then(|current_dir| {
+            current_dir
+                .ancestors()
+                .map(|dir| dir.join(".quantum").join("config.toml"))
+                .find(|path| path.exists())
+        });
+        
+        if let Some(project_path) = project_path.filter(|path| *path != user_path) {
+            if let Some(project) = Self::load_file(&project_path)? {
+                config.merge(project);
+            }
+        }
+        
+        Ok(config)
+    }
+    
+    /// Load a single config file.
+    ///
+    /// Relative source directories are resolved against the directory that
+    /// contains the `.quantum` directory.
+    fn load_file(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        
+        let mut config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        
+        let base = path
+            .parent()
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new("."));
+        
+        for source in config.source.values_mut() {
+            if let Some(directory) = &source.directory {
+                source.directory = Some(base.join(directory));
+            }
+        }
+        
+        Ok(Some(config))
+    }
+    
+    /// Merge a more specific configuration into this one
+    fn merge(&mut self, other: Config) {
+        self.net.offline |= other.net.offline;
+        self.registries.extend(other.registries);
+        self.source.extend(other.source);
+    }
+    
+    /// Follow the `replace-with` chain of a source.
+    ///
+    /// # Arguments
+    /// * `name` - The source name, e.g. [`DEFAULT_REGISTRY_SOURCE`] or `git+<url>`
+    ///
+    /// # Returns
+    /// The name and definition of the source that finally replaces `name`,
+    /// if it is replaced at all
+    pub fn replacement(&self, name: &str) -> Result<Option<(&str, &SourceConfig)>> {
+        let mut current = name;
+        let mut replacement = None;
+        
+        for _ in 0..MAX_REPLACEMENTS {
+            let Some(next) = self.source.get(current).and_then(|s| s.replace_with.as_deref()) else {
+                return Ok(replacement);
+            };
+            
+            let source = self.source.get(next).ok_or_else(|| anyhow::anyhow!(
+                "Source {} is replaced with undefined source {}", current, next
+            ))?;
+            
+            current = next;
+            replacement = Some((next, source));
+        }
+        
+        anyhow::bail!("Source replacement for {} does not terminate", name)
+    }
+    
+    /// Get the URL of a registry source defined in the configuration.
+    ///
+    /// # Arguments
+    /// * `name` - A name from `[registries]` or a `[source]` with a `registry` URL
+    pub fn registry_url(&self, name: &str) -> Option<&str> {
+        self.registries
+            .get(name)
+            .map(|registry| registry.url.as_str())
+            .or_else(|| self.source.get(name)?.registry.as_deref())
+    }
+}
+
+/// Get the source name of a registry as written in a dependency's
+/// `registry` key: a name from `[registries]`, or a registry URL
+pub fn registry_source(registry: Option<&str>) -> String {
+    match registry {
+        None => DEFAULT_REGISTRY_SOURCE.to_string(),
+        Some(url) if url.contains("://") => format!("registry+{}", url),
+        Some(name) => name.to_string(),
+    }
+}
+
+/// Get the source name of a git repository
+pub fn git_source(url: &str) -> String {
+    format!("git+{}", url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_source_replacement() {
+        let config: Config = toml::from_str(r#"
+            [registries.internal]
+            url = "https://registry.internal.example"
+            
+            [source.quantum-registry]
+            replace-with = "mirror"
+            
+            [source.mirror]
+            replace-with = "internal-mirror"
+            
+            [source.internal-mirror]
+            registry = "https://mirror.internal.example"
+        "#).unwrap();
+        
+        let (name, source) = config.replacement(DEFAULT_REGISTRY_SOURCE).unwrap().unwrap();
+        assert_eq!(name, "internal-mirror");
+        assert_eq!(source.registry.as_deref(), Some("https://mirror.internal.example"));
+        assert!(config.replacement("internal").unwrap().is_none());
+        assert_eq!(config.registry_url("internal"), Some("https://registry.internal.example"));
+        
+        assert_eq!(registry_source(None), DEFAULT_REGISTRY_SOURCE);
+        assert_eq!(registry_source(Some("internal")), "internal");
+        assert_eq!(registry_source(Some("https://r.example")), "registry+https://r.example");
+    }
+    
+    #[test]
+    fn test_undefined_replacement() {
+        let config: Config = toml::from_str(r#"
+            [source.quantum-registry]
+            replace-with = "missing"
+        "#).unwrap();
+        
+        let error = config.replacement(DEFAULT_REGISTRY_SOURCE).unwrap_err();
+        assert!(error.to_string().contains("undefined source missing"));
+    }
+}
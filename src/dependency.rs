@@ -2,198 +2,1164 @@
 //!
 //! Dependency resolution and installation.
 
+use crate::cache::{self, CacheLock};
+use crate::checksum::{self, PackageChecksums};
+use crate::config::{self, Config, DEFAULT_REGISTRY_SOURCE};
+use crate::git::{self, GitDatabase, GitReference};
+use crate::lockfile::{LockedDependency, Lockfile};
 use crate::manifest::{Dependency, DetailedDependency, Manifest};
-use crate::registry::Registry;
+use crate::paths;
+use crate::registry::{Registry, VersionMetadata};
+use crate::solver::{DependencyProvider, Solver};
+use crate::version::{self, Version, VersionReq};
+use crate::workspace::Workspace;
 use anyhow::{Context, Result};
-use std::collections::{HashMap, VecDeque};
+use colored::Colorize;
+use futures::StreamExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Maximum number of registry downloads running at once
+const MAX_CONCURRENT_DOWNLOADS: usize = 8;
 
 /// Dependency resolver
 pub struct DependencyResolver {
+    /// Client for the default registry
     registry: Registry,
+    /// Clients for other registries and mirrors, by URL
+    registries: Mutex<HashMap<String, Registry>>,
     cache_dir: PathBuf,
+    /// Previously locked versions to prefer
+    lockfile: Option<Lockfile>,
+    /// Forbid network access
+    offline: bool,
+    /// Exact versions requested for individual packages
+    precise: HashMap<String, Version>,
+    /// Named registries and source replacements from the configuration
+    config: Config,
+    /// Redirect replaced sources to vendored directories
+    vendored: bool,
+}
+
+/// A registry package version selected by the solver
+struct RegistryPackage {
+    name: String,
+    version: String,
+    /// Registry source name, see [`config::registry_source`]
+    source: String,
+}
+
+/// Options controlling how a build may use and update Quantum.lock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResolveOptions {
+    /// Fail if Quantum.lock is missing or would change
+    pub locked: bool,
+    /// Forbid network access
+    pub offline: bool,
 }
 
 impl DependencyResolver {
-    /// Create a new dependency resolver
+    /// Create a new dependency resolver.
+    ///
+    /// Network access is disabled when `net.offline` is set in the config.
     pub fn new(registry_url: Option<&str>) -> Result<Self> {
+        Self::with_config(registry_url, Config::load()?, paths::cache_dir()?)
+    }
+    
+    /// Create a dependency resolver from an already loaded configuration
+    /// and an explicit cache directory.
+    ///
+    /// # Arguments
+    /// * `registry_url` - URL of the default registry, if not the official one
+    /// * `config` - The CLI configuration
+    /// * `cache_dir` - Root of the package cache
+    pub(crate) fn with_config(registry_url: Option<&str>, config: Config, cache_dir: PathBuf) -> Result<Self> {
         let registry = Registry::new(registry_url)?;
-        
-        // Get cache directory
-        let cache_dir = get_cache_dir()?;
         std::fs::create_dir_all(&cache_dir)?;
         
         Ok(Self {
             registry,
+            registries: Mutex::new(HashMap::new()),
             cache_dir,
+            lockfile: None,
+            offline: config.net.offline,
+            precise: HashMap::new(),
+            config,
+            vendored: true,
         })
     }
     
-    /// Resolve all dependencies for a manifest
-    pub async fn resolve(&self, manifest: &Manifest) -> Result<ResolvedDependencies> {
-        let mut resolved = ResolvedDependencies::new();
-        let mut to_resolve = VecDeque::new();
+    /// Prefer the versions pinned in a lockfile
+    pub fn with_lockfile(mut self, lockfile: Option<Lockfile>) -> Self {
+        self.lockfile = lockfile;
+        self
+    }
+    
+    /// Forbid network access, in addition to the `net.offline` config
+    /// setting; registry and git dependencies must then be cached
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline |= offline;
+        self
+    }
+    
+    /// Require an exact version of a registry package
+    pub fn with_precise(mut self, name: &str, version: Version) -> Self {
+        self.precise.insert(name.to_string(), version);
+        self
+    }
+    
+    /// Ignore source replacements that point to vendored directories, so
+    /// that packages are fetched from their original sources
+    pub fn without_vendored_sources(mut self) -> Self {
+        self.vendored = false;
+        self
+    }
+    
+    /// Get the vendored directory that replaces a source.
+    ///
+    /// # Arguments
+    /// * `source` - The source name, e.g. `git+<url>`
+    fn vendored_directory(&self, source: &str) -> Result<Option<PathBuf>> {
+        if !self.vendored {
+            return Ok(None);
+        }
         
-        // Add direct dependencies
-        for (name, dep) in &manifest.dependencies {
-            to_resolve.push_back((name.clone(), dep.clone(), 0));
+        Ok(self.config.replacement(source)?.and_then(|(_, s)| s.directory.clone()))
+    }
+    
+    /// Get the URL of a registry source as declared, before replacement
+    fn source_url(&self, source: &str) -> Result<String> {
+        if source == DEFAULT_REGISTRY_SOURCE {
+            return Ok(self.registry.url().to_string());
         }
         
-        // Resolve dependencies recursively
-        while let Some((name, dep, depth)) = to_resolve.pop_front() {
-            if depth > 100 {
-                anyhow::bail!("Dependency depth limit exceeded (possible circular dependency)");
-            }
-            
-            if resolved.contains(&name) {
+        if let Some(url) = self.config.registry_url(source) {
+            return Ok(url.to_string());
+        }
+        
+        match source.strip_prefix("registry+") {
+            Some(url) => Ok(url.to_string()),
+            None => anyhow::bail!(
+                "Registry {} is not defined; add it to [registries] in .quantum/config.toml",
+                source
+            ),
+        }
+    }
+    
+    /// Get the client for a registry source, following source replacement
+    /// to mirrors. One client is created per registry and reused.
+    ///
+    /// # Arguments
+    /// * `source` - The registry source name, see [`config::registry_source`]
+    fn registry(&self, source: &str) -> Result<Registry> {
+        let name = match self.config.replacement(source)? {
+            Some((name, replacement)) if replacement.directory.is_none() => name,
+            _ => source,
+        };
+        
+        let url = self.source_url(name)?;
+        
+        if url == self.registry.url() {
+            return Ok(self.registry.clone());
+        }
+        
+        let mut registries = self.registries.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        
+        if let Some(registry) = registries.get(&url) {
+            return Ok(registry.clone());
+        }
+        
+        let registry = Registry::new(Some(&url))?;
+        registries.insert(url, registry.clone());
+        
+        Ok(registry)
+    }
+    
+    /// Get the lockfile entry for a package
+    fn locked(&self, name: &str) -> Option<&LockedDependency> {
+        self.lockfile.as_ref()?.dependencies.get(name)
+    }
+    
+    /// Get the version of a registry package pinned in the lockfile
+    fn locked_version(&self, name: &str) -> Option<Version> {
+        let locked = self.locked(name)?;
+        
+        if locked.source != "registry" {
+            return None;
+        }
+        
+        version::parse_version(&locked.version).ok()
+    }
+    
+    /// Get the cache directory of a registry source.
+    ///
+    /// Mirrors share the cache of the source they replace, since they serve
+    /// the same packages.
+    fn registry_cache_dir(&self, source: &str) -> PathBuf {
+        if source == DEFAULT_REGISTRY_SOURCE {
+            self.cache_dir.clone()
+        } else {
+            self.cache_dir.join("registries").join(source.replace(['/', ':'], "_"))
+        }
+    }
+    
+    /// Get the cache directory for a registry package version
+    pub(crate) fn registry_cache_path(&self, source: &str, name: &str, version: &str) -> PathBuf {
+        self.registry_cache_dir(source).join(format!("{}-{}", name, version))
+    }
+    
+    /// List the versions of a registry package available in the cache.
+    ///
+    /// Only complete entries, with both the archive and its extracted files,
+    /// are considered. The solver calls this from async code, so the cache
+    /// is not locked: an entry only has both once it is complete.
+    fn cached_versions(&self, source: &str, name: &str) -> Result<Vec<Version>> {
+        let cache_dir = self.registry_cache_dir(source);
+        let prefix = format!("{}-", name);
+        let mut versions = Vec::new();
+        
+        if !cache_dir.exists() {
+            return Ok(versions);
+        }
+        
+        for entry in std::fs::read_dir(&cache_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some(version) = file_name.to_str().and_then(|f| f.strip_prefix(&prefix)) else {
                 continue;
-            }
+            };
             
-            let dep_info = self.resolve_single(&name, &dep).await?;
+            let Ok(parsed) = version::parse_version(version) else {
+                continue;
+            };
             
-            // Add transitive dependencies
-            for (trans_name, trans_dep) in &dep_info.manifest.dependencies {
-                to_resolve.push_back((trans_name.clone(), trans_dep.clone(), depth + 1));
+            if entry.file_type()?.is_dir() && self.registry_archive_path(source, name, version).exists() {
+                versions.push(parsed);
             }
+        }
+        
+        Ok(versions)
+    }
+    
+    /// Get the cached archive of a registry package version
+    pub(crate) fn registry_archive_path(&self, source: &str, name: &str, version: &str) -> PathBuf {
+        self.registry_cache_dir(source).join(format!("{}-{}.tar", name, version))
+    }
+    
+    /// Resolve all dependencies of the members of a workspace.
+    ///
+    /// Selects one version of every package in the dependency graph with the
+    /// version solver, then fetches the selected versions. Dev-dependencies of
+    /// the members are always resolved so Quantum.lock is the same for
+    /// builds and tests; dev-dependencies of dependencies never are.
+    ///
+    /// # Arguments
+    /// * `workspace` - The workspace, or a single package
+    pub async fn resolve(&self, workspace: &Workspace) -> Result<ResolvedDependencies> {
+        let mut provider = SourceProvider::new(self);
+        provider.register_patches(&workspace.patch, &workspace.root)?;
+        
+        let mut requirements = Vec::new();
+        
+        for member in &workspace.members {
+            requirements.extend(provider.register_dependencies(member.manifest.all_dependencies(), &member.root)?);
+        }
+        
+        let root_name = match workspace.members.as_slice() {
+            [package] => package.name().to_string(),
+            _ => "the workspace".to_string(),
+        };
+        
+        let solution = match Solver::new(&mut provider, &root_name).solve(requirements).await {
+            Ok(solution) => solution,
+            Err(error) if self.offline => return Err(provider.explain_offline_failure(error)),
+            Err(error) => return Err(error),
+        };
+        
+        for (name, version) in &solution {
+            if provider.is_yanked(name, version) {
+                println!(
+                    "{} {} v{} has been yanked from the registry and is only used because it is pinned",
+                    "warning:".yellow().bold(), name, version
+                );
+            }
+        }
+        
+        for patch in provider.patches.iter().filter(|patch| !patch.used) {
+            println!(
+                "{} patch for {} in [patch.{}] was not used in the dependency graph",
+                "warning:".yellow().bold(), patch.name, patch.table
+            );
+        }
+        
+        let mut resolved = ResolvedDependencies::new();
+        let mut downloads = Vec::new();
+        
+        for member in &workspace.members {
+            let manifest = &member.manifest;
+            let roots = package_names(&manifest.dependencies);
+            let dev_roots = package_names(&manifest.dev_dependencies)
+                .into_iter()
+                .filter(|name| !roots.contains(name))
+                .collect();
             
+            resolved.roots.insert(member.name().to_string(), roots);
+            resolved.dev_roots.insert(member.name().to_string(), dev_roots);
+        }
+        
+        for (name, version) in solution {
+            match provider.loaded.remove(&name) {
+                Some(dep_info) => resolved.add(name, dep_info),
+                None => downloads.push(RegistryPackage {
+                    source: provider.registry_source(&name)?,
+                    version: version.to_string(),
+                    name,
+                }),
+            }
+        }
+        
+        for (name, dep_info) in self.fetch_registry_dependencies(&downloads).await? {
             resolved.add(name, dep_info);
         }
         
+        if let Some(cycle) = resolved.find_cycle() {
+            anyhow::bail!("Cyclic package dependency: {}", cycle.join(" -> "));
+        }
+        
+        let _lock = CacheLock::acquire(&self.cache_dir).await?;
+        cache::record_use(&self.cache_dir, resolved.all().values().map(|info| info.path.as_path()))?;
+        
         Ok(resolved)
     }
     
-    /// Resolve a single dependency
-    async fn resolve_single(&self, name: &str, dep: &Dependency) -> Result<DependencyInfo> {
-        match dep {
-            Dependency::Simple(version) => {
-                self.resolve_registry_dependency(name, version).await
-            }
-            Dependency::Detailed(detailed) => {
-                if let Some(path) = &detailed.path {
-                    self.resolve_path_dependency(name, path)
-                } else if let Some(git) = &detailed.git {
-                    self.resolve_git_dependency(name, git, detailed).await
-                } else if let Some(version) = &detailed.version {
-                    self.resolve_registry_dependency(name, version).await
-                } else {
-                    anyhow::bail!("Invalid dependency specification for {}", name)
+    /// Fetch registry dependencies concurrently.
+    ///
+    /// At most [`MAX_CONCURRENT_DOWNLOADS`] archives are downloaded at once,
+    /// each with its own progress bar. Results are returned in name order.
+    async fn fetch_registry_dependencies(
+        &self,
+        packages: &[RegistryPackage],
+    ) -> Result<Vec<(String, DependencyInfo)>> {
+        let progress = MultiProgress::new();
+        
+        let mut results = futures::stream::iter(packages)
+            .map(|package| {
+                let progress = &progress;
+                async move {
+                    let result = self.resolve_registry_dependency(package, Some(progress)).await;
+                    (package.name.clone(), result)
                 }
-            }
-        }
+            })
+            .buffer_unordered(MAX_CONCURRENT_DOWNLOADS)
+            .collect::<Vec<_>>()
+            .await;
+        
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        results
+            .into_iter()
+            .map(|(name, result)| result.map(|dep_info| (name, dep_info)))
+            .collect()
     }
     
-    /// Resolve a registry dependency
-    async fn resolve_registry_dependency(&self, name: &str, version: &str) -> Result<DependencyInfo> {
+    /// Fetch an exact version of a registry dependency.
+    ///
+    /// The archive is kept next to its extracted files so that later builds
+    /// can verify both against the checksum recorded in Quantum.lock.
+    ///
+    /// # Arguments
+    /// * `package` - The exact version to fetch and its registry source
+    /// * `progress` - Display to add a download progress bar to
+    async fn resolve_registry_dependency(
+        &self,
+        package: &RegistryPackage,
+        progress: Option<&MultiProgress>,
+    ) -> Result<DependencyInfo> {
+        let RegistryPackage { name, version, source } = package;
+        let cache_path = self.registry_cache_path(source, name, version);
+        let archive_path = self.registry_archive_path(source, name, version);
+        
         // Check cache first
-        let cache_path = self.cache_dir.join(format!("{}-{}", name, version));
+        let downloaded = if cache_path.exists() && archive_path.exists() {
+            None
+        } else if self.offline {
+            anyhow::bail!("{} v{} is not cached and network access is disabled", name, version);
+        } else {
+            // Download from registry
+            let pb = match progress {
+                Some(progress) => progress.add(ProgressBar::new(0)),
+                None => ProgressBar::with_draw_target(None, ProgressDrawTarget::hidden()),
+            };
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("{prefix:>24.cyan} [{bar:30.cyan/blue}] {bytes}/{total_bytes}")
+                    .unwrap()
+                    .progress_chars("#>-")
+            );
+            pb.set_prefix(format!("{} v{}", name, version));
+            
+            let archive = self.registry(source)?.download(name, version, &pb).await;
+            pb.finish_and_clear();
+            Some(archive?)
+        };
+        
+        // Extracting and hashing would stall the other downloads
+        let cache_dir = self.cache_dir.clone();
+        let extracted = cache_path.clone();
+        let checksums = tokio::task::spawn_blocking(move || -> Result<(String, bool)> {
+            // Other processes may be using the same cache, but not during the download
+            let _lock = CacheLock::acquire_blocking(&cache_dir)?;
+            
+            let archive = match downloaded {
+                Some(archive) => {
+                    // The archive is written first, so an extracted entry always has one
+                    cache::write_atomic(&archive_path, &archive)?;
+                    cache::extract_archive(&archive, &extracted)?;
+                    archive
+                }
+                None => std::fs::read(&archive_path)
+                    .with_context(|| format!("Failed to read {}", archive_path.display()))?,
+            };
+            
+            let unmodified = checksum::archive_tree_checksum(&archive)? == checksum::tree_checksum(&extracted)?;
+            Ok((checksum::archive_checksum(&archive), unmodified))
+        });
+        let (checksum, unmodified) = checksums.await.context("Failed to unpack a registry package")??;
+        
+        self.load_registry_dependency(package, checksum, unmodified, &cache_path)
+    }
+    
+    /// Verify a cached registry dependency and load it.
+    ///
+    /// # Arguments
+    /// * `package` - The registry package
+    /// * `checksum` - Checksum of the package's archive
+    /// * `unmodified` - Whether the extracted files match the archive
+    /// * `cache_path` - Directory of the extracted files
+    fn load_registry_dependency(
+        &self,
+        package: &RegistryPackage,
+        checksum: String,
+        unmodified: bool,
+        cache_path: &Path,
+    ) -> Result<DependencyInfo> {
+        let RegistryPackage { name, version, source } = package;
         
-        if cache_path.exists() {
-            return self.load_cached_dependency(&cache_path);
+        if let Some(locked) = self.locked(name).filter(|l| l.version == *version) {
+            if let Some(expected) = locked.checksum.as_deref().filter(|c| *c != checksum) {
+                anyhow::bail!(
+                    "Checksum mismatch for {} v{}: Quantum.lock expects {}, archive has {}",
+                    name, version, expected, checksum
+                );
+            }
         }
         
-        // Download from registry
-        let archive = self.registry.download(name, version).await?;
+        if !unmodified {
+            anyhow::bail!(
+                "Cached files of {} v{} were modified; remove {} to download them again",
+                name, version, cache_path.display()
+            );
+        }
         
-        // Extract to cache
-        extract_archive(&archive, &cache_path)?;
+        let mut dep_info = self.load_dependency(name, cache_path, DependencySource::Registry)?;
+        dep_info.source_url = Some(self.source_url(source)?);
+        dep_info.registry = Some(source.clone());
+        dep_info.checksum = checksum;
         
-        self.load_cached_dependency(&cache_path)
+        Ok(dep_info)
     }
     
-    /// Resolve a path dependency
-    fn resolve_path_dependency(&self, name: &str, path: &str) -> Result<DependencyInfo> {
-        let dep_path = PathBuf::from(path);
+    /// Load a dependency from a vendored directory.
+    ///
+    /// The vendored files must match the checksum file written by
+    /// `quantum vendor`, and the package checksum must match Quantum.lock.
+    ///
+    /// # Arguments
+    /// * `name` - The dependency name
+    /// * `directory` - The vendored directory replacing the declared source
+    /// * `source` - The declared source of the dependency
+    fn resolve_vendored_dependency(
+        &self,
+        name: &str,
+        directory: &Path,
+        source: DependencySource,
+    ) -> Result<DependencyInfo> {
+        let vendor_path = directory.join(name);
         
-        if !dep_path.exists() {
-            anyhow::bail!("Path dependency not found: {}", path);
+        if !vendor_path.join("Quantum.toml").exists() {
+            anyhow::bail!(
+                "Package {} is not vendored in {}; run `quantum vendor` to update it",
+                name, directory.display()
+            );
         }
         
-        let manifest_path = dep_path.join("Quantum.toml");
-        let manifest = Manifest::load(&manifest_path)?;
+        let checksums = PackageChecksums::load(&vendor_path)?;
+        checksums.verify(&vendor_path)
+            .with_context(|| format!("Vendored package {} does not match its checksums", name))?;
         
-        Ok(DependencyInfo {
-            name: name.to_string(),
-            version: manifest.package.version.clone(),
-            path: dep_path,
-            manifest,
-            source: DependencySource::Path,
-        })
+        let mut dep_info = self.load_dependency(name, &vendor_path, source)?;
+        
+        if let Some(locked) = self.locked(name).filter(|l| l.version == dep_info.version) {
+            if let Some(expected) = locked.checksum.as_deref().filter(|c| *c != checksums.package) {
+                anyhow::bail!(
+                    "Checksum mismatch for vendored {} v{}: Quantum.lock expects {}, vendored copy has {}",
+                    name, dep_info.version, expected, checksums.package
+                );
+            }
+        }
+        
+        dep_info.source_url = checksums.source_url;
+        dep_info.checksum = checksums.package;
+        dep_info.commit = checksums.commit;
+        
+        Ok(dep_info)
+    }
+    
+    /// Resolve a path dependency.
+    ///
+    /// # Arguments
+    /// * `name` - The dependency name
+    /// * `dep_path` - Canonical path of the dependency's root directory
+    fn resolve_path_dependency(&self, name: &str, dep_path: &Path) -> Result<DependencyInfo> {
+        let mut dep_info = self.load_dependency(name, dep_path, DependencySource::Path)
+            .with_context(|| format!("Failed to load path dependency {} from {}", name, dep_path.display()))?;
+        dep_info.source_url = Some(dep_path.display().to_string());
+        dep_info.checksum = checksum::tree_checksum(dep_path)?;
+        
+        Ok(dep_info)
     }
     
     /// Resolve a git dependency from a remote repository.
     ///
-    /// Clones the repository, checks out the specified ref, and loads the manifest.
+    /// Fetches the requested ref into the shared git database, checks out
+    /// its commit, and loads the manifest. A branch that is locked stays at
+    /// the locked commit; an unlocked one is fetched again. The checksums of
+    /// the checkout are verified every time it is loaded, and a checkout
+    /// whose commit matches Quantum.lock must also match the locked checksum.
     ///
     /// # Arguments
-    /// * `_name` - The dependency name
+    /// * `name` - The dependency name
     /// * `git_url` - The git repository URL
     /// * `detailed` - Detailed dependency specification with branch/tag/rev
     async fn resolve_git_dependency(
         &self,
-        _name: &str,
+        name: &str,
         git_url: &str,
         detailed: &DetailedDependency,
     ) -> Result<DependencyInfo> {
-        // Create cache key from git URL and ref
-        let ref_str = detailed.branch.as_deref()
-            .or(detailed.tag.as_deref())
-            .or(detailed.rev.as_deref())
-            .unwrap_or("HEAD");
+        let reference = GitReference::from_dependency(detailed)?;
+        let database = GitDatabase::new(&self.cache_dir.join("git"), git_url);
+        
+        // A locked branch stays at its commit until `quantum update`
+        let locked_commit = self.locked(name)
+            .filter(|locked| reference.is_branch() && locked.source_url.as_deref() == Some(git_url))
+            .and_then(|locked| locked.commit.clone());
         
-        let cache_key = format!("{}-{}", 
-            git_url.replace(['/', ':'], "_"),
-            ref_str.replace('/', "_")
-        );
+        let cache_dir = self.cache_dir.clone();
+        let offline = self.offline;
+        let dependency = name.to_string();
         
-        let cache_path = self.cache_dir.join(cache_key);
+        // Git guards its own database against concurrent fetches, so only
+        // the checkouts shared with other processes are locked
+        let checkout = tokio::task::spawn_blocking(move || -> Result<(String, PathBuf, PackageChecksums)> {
+            let commit = match locked_commit {
+                Some(commit) => {
+                    database.ensure(&commit, &reference, offline)?;
+                    commit
+                }
+                None => database.resolve(&reference, offline)?,
+            };
+            
+            let _lock = CacheLock::acquire_blocking(&cache_dir)?;
+            let cache_path = database.checkout(&commit)?;
+            let checksums = PackageChecksums::load(&cache_path)?;
+            checksums.verify(&cache_path).with_context(|| format!(
+                "Checked out files of git dependency {} were modified; remove {} to check it out again",
+                dependency, cache_path.display()
+            ))?;
+            
+            Ok((commit, cache_path, checksums))
+        });
+        let (commit, cache_path, checksums) = checkout.await.context("Failed to check out a git dependency")??;
+        let checksum = checksums.package;
         
-        if cache_path.exists() {
-            return self.load_cached_dependency(&cache_path);
+        if let Some(locked) = self.locked(name).filter(|l| l.commit.as_deref() == Some(commit.as_str())) {
+            if let Some(expected) = locked.checksum.as_deref().filter(|c| *c != checksum) {
+                anyhow::bail!(
+                    "Checksum mismatch for git dependency {} at {}: Quantum.lock expects {}, checkout has {}",
+                    name, commit, expected, checksum
+                );
+            }
         }
         
-        // Clone repository
-        clone_git_repo(git_url, &cache_path, detailed)?;
+        let mut dep_info = self.load_dependency(name, &cache_path, DependencySource::Git)?;
+        dep_info.source_url = Some(git_url.to_string());
+        dep_info.checksum = checksum;
+        dep_info.commit = Some(commit);
         
-        self.load_cached_dependency(&cache_path)
+        Ok(dep_info)
     }
     
-    /// Load dependency from cache
-    fn load_cached_dependency(&self, path: &Path) -> Result<DependencyInfo> {
+    /// Load a dependency from a directory holding its files.
+    ///
+    /// # Arguments
+    /// * `name` - The declared package name, which the manifest must match
+    /// * `path` - The cached, checked out, vendored or local package directory
+    /// * `source` - Where the files came from
+    fn load_dependency(&self, name: &str, path: &Path, source: DependencySource) -> Result<DependencyInfo> {
         let manifest_path = path.join("Quantum.toml");
-        let manifest = Manifest::load(&manifest_path)?;
+        let manifest = Manifest::load(&manifest_path)
+            .with_context(|| format!("Failed to load {}", manifest_path.display()))?;
+        
+        if manifest.package.name != name {
+            anyhow::bail!(
+                "Dependency {} was found at {}, but its manifest is for package {}; use `package = \"{}\"` to depend on it under another name",
+                name, path.display(), manifest.package.name, manifest.package.name
+            );
+        }
         
         Ok(DependencyInfo {
-            name: manifest.package.name.clone(),
+            name: name.to_string(),
             version: manifest.package.version.clone(),
             path: path.to_path_buf(),
             manifest,
-            source: DependencySource::Registry,
+            source,
+            source_url: None,
+            checksum: String::new(),
+            commit: None,
+            registry: None,
         })
     }
 }
 
-/// Resolved dependencies
+/// A dependency as declared by a particular manifest
+#[derive(Clone)]
+struct DeclaredDependency {
+    /// Key of the dependency in its table, which differs from the package
+    /// name when the dependency is renamed
+    key: String,
+    dependency: Dependency,
+    /// Canonical root directory of a path dependency
+    path: Option<PathBuf>,
+    /// Manifest that declared the dependency
+    declared_in: PathBuf,
+}
+
+impl DeclaredDependency {
+    /// Describe the source of the dependency, for comparing declarations
+    fn source(&self) -> String {
+        if let Some(path) = &self.path {
+            return path.display().to_string();
+        }
+        
+        match &self.dependency {
+            Dependency::Detailed(detailed @ DetailedDependency { git: Some(git), .. }) => {
+                match GitReference::from_dependency(detailed) {
+                    Ok(reference) => format!("{} ({})", git, reference),
+                    Err(_) => git.clone(),
+                }
+            }
+            Dependency::Detailed(detailed) => config::registry_source(detailed.registry.as_deref()),
+            Dependency::Simple(_) => DEFAULT_REGISTRY_SOURCE.to_string(),
+        }
+    }
+}
+
+/// A `[patch]` entry of the root manifest
+struct Patch {
+    /// Key of the `[patch]` table, as written
+    table: String,
+    /// Names of the sources the table may refer to
+    sources: Vec<String>,
+    /// Name of the patched package
+    name: String,
+    /// The path or git dependency used instead
+    declared: DeclaredDependency,
+    /// Whether the patch replaced a dependency in the graph
+    used: bool,
+}
+
+/// Dependency provider backed by the registry, local paths and git.
+///
+/// Remembers the source declared for every package it has seen so the
+/// solver can work with plain names and versions.
+struct SourceProvider<'a> {
+    resolver: &'a DependencyResolver,
+    /// Declared source of every package seen so far
+    sources: HashMap<String, DeclaredDependency>,
+    /// Registry metadata by package name
+    registry_versions: HashMap<String, Vec<VersionMetadata>>,
+    /// Loaded path and git dependencies by package name
+    loaded: HashMap<String, DependencyInfo>,
+    /// Patches from the root manifest
+    patches: Vec<Patch>,
+    /// Version requirements on every package, with the manifest declaring each
+    requirements: HashMap<String, Vec<(VersionReq, PathBuf)>>,
+}
+
+impl<'a> SourceProvider<'a> {
+    fn new(resolver: &'a DependencyResolver) -> Self {
+        Self {
+            resolver,
+            sources: HashMap::new(),
+            registry_versions: HashMap::new(),
+            loaded: HashMap::new(),
+            patches: Vec::new(),
+            requirements: HashMap::new(),
+        }
+    }
+    
+    /// Record the `[patch]` tables of the root manifest.
+    ///
+    /// A table key is a registry name or URL, or a git URL; a URL may refer
+    /// to either, so it matches both kinds of source.
+    fn register_patches(
+        &mut self,
+        patches: &HashMap<String, HashMap<String, Dependency>>,
+        manifest_dir: &Path,
+    ) -> Result<()> {
+        for (table, dependencies) in patches {
+            let mut sources = vec![config::registry_source(Some(table))];
+            
+            if table.contains("://") {
+                sources.push(config::git_source(table));
+            }
+            
+            for (name, dep) in dependencies {
+                self.patches.push(Patch {
+                    table: table.clone(),
+                    sources: sources.clone(),
+                    name: dep.package_name(name).to_string(),
+                    declared: declare(name, dep, manifest_dir)?,
+                    used: false,
+                });
+            }
+        }
+        
+        self.patches.sort_by(|a, b| (&a.table, &a.name).cmp(&(&b.table, &b.name)));
+        
+        Ok(())
+    }
+    
+    /// Find the patch that overrides a dependency, marking it as used
+    fn patch(&mut self, name: &str, dep: &Dependency) -> Option<DeclaredDependency> {
+        let source = match dep {
+            Dependency::Detailed(DetailedDependency { path: Some(_), .. }) => return None,
+            Dependency::Detailed(DetailedDependency { git: Some(git), .. }) => config::git_source(git),
+            Dependency::Detailed(detailed) => config::registry_source(detailed.registry.as_deref()),
+            Dependency::Simple(_) => DEFAULT_REGISTRY_SOURCE.to_string(),
+        };
+        
+        let patch = self.patches
+            .iter_mut()
+            .find(|patch| patch.name == name && patch.sources.contains(&source))?;
+        patch.used = true;
+        
+        Some(patch.declared.clone())
+    }
+    
+    /// Record the declared sources of a dependency table and convert it into
+    /// solver requirements, ordered by name.
+    ///
+    /// Renamed dependencies are recorded under their package name. Path
+    /// dependencies are resolved relative to `manifest_dir` and
+    /// canonicalized, so different spellings of one path are one package.
+    /// Patched dependencies take the source of their patch instead. Every
+    /// declaration of a package, renamed or not, must have the same source.
+    fn register_dependencies<'d>(
+        &mut self,
+        dependencies: impl IntoIterator<Item = (&'d String, &'d Dependency)>,
+        manifest_dir: &Path,
+    ) -> Result<Vec<(String, VersionReq)>> {
+        let mut requirements = Vec::new();
+        
+        for (key, dep) in dependencies {
+            let name = dep.package_name(key);
+            let requirement = match dep.version_requirement() {
+                Some(req) => version::parse_requirement(req)
+                    .with_context(|| format!("Invalid version requirement for {}", name))?,
+                None => VersionReq::STAR,
+            };
+            
+            let declared = match self.patch(name, dep) {
+                Some(patch) => DeclaredDependency { key: key.clone(), ..patch },
+                None => declare(key, dep, manifest_dir)?,
+            };
+            
+            if let (Some(existing), Some(path)) = (self.sources.get(name), &declared.path) {
+                if let Some(existing_path) = existing.path.as_ref().filter(|p| *p != path) {
+                    anyhow::bail!(
+                        "Package {} is declared with two different paths: {} (in {}) and {} (in {})",
+                        name,
+                        existing_path.display(),
+                        existing.declared_in.display(),
+                        path.display(),
+                        declared.declared_in.display()
+                    );
+                }
+            }
+            
+            if let Some(existing) = self.sources.get(name) {
+                let (existing_source, source) = (existing.source(), declared.source());
+                
+                if existing_source != source {
+                    anyhow::bail!(
+                        "Package {} is declared from two different sources: {} as {} (in {}) and {} as {} (in {})",
+                        name,
+                        existing_source,
+                        existing.key,
+                        existing.declared_in.display(),
+                        source,
+                        key,
+                        declared.declared_in.display()
+                    );
+                }
+            }
+            
+            self.sources.entry(name.to_string()).or_insert(declared);
+            self.requirements
+                .entry(name.to_string())
+                .or_default()
+                .push((requirement.clone(), manifest_dir.join("Quantum.toml")));
+            
+            requirements.push((name.to_string(), requirement));
+        }
+        
+        requirements.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        Ok(requirements)
+    }
+    
+    /// Get the declared source of a package
+    fn source(&self, name: &str) -> Result<DeclaredDependency> {
+        self.sources
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown dependency: {}", name))
+    }
+    
+    /// Get the vendored directory that replaces the declared source of a
+    /// registry or git dependency
+    fn vendored_directory(&self, declared: &DeclaredDependency) -> Result<Option<PathBuf>> {
+        match &declared.dependency {
+            _ if declared.path.is_some() => Ok(None),
+            Dependency::Detailed(DetailedDependency { git: Some(git), .. }) => {
+                self.resolver.vendored_directory(&config::git_source(git))
+            }
+            Dependency::Detailed(detailed) => {
+                self.resolver.vendored_directory(&config::registry_source(detailed.registry.as_deref()))
+            }
+            Dependency::Simple(_) => self.resolver.vendored_directory(DEFAULT_REGISTRY_SOURCE),
+        }
+    }
+    
+    /// Get the registry source declared for a registry package
+    fn registry_source(&self, name: &str) -> Result<String> {
+        Ok(match &self.source(name)?.dependency {
+            Dependency::Detailed(detailed) => config::registry_source(detailed.registry.as_deref()),
+            Dependency::Simple(_) => DEFAULT_REGISTRY_SOURCE.to_string(),
+        })
+    }
+    
+    /// Load a path, git or vendored dependency once
+    async fn load_local(&mut self, name: &str, declared: &DeclaredDependency) -> Result<&DependencyInfo> {
+        if !self.loaded.contains_key(name) {
+            let vendored = self.vendored_directory(declared)?;
+            
+            let dep_info = match (&declared.dependency, vendored) {
+                (Dependency::Detailed(DetailedDependency { git: Some(_), .. }), Some(directory)) => {
+                    self.resolver.resolve_vendored_dependency(name, &directory, DependencySource::Git)?
+                }
+                (_, Some(directory)) => {
+                    let mut dep_info = self.resolver
+                        .resolve_vendored_dependency(name, &directory, DependencySource::Registry)?;
+                    dep_info.registry = Some(self.registry_source(name)?);
+                    dep_info
+                }
+                (Dependency::Detailed(detailed), None) => self.load_source(name, declared, detailed).await?,
+                (Dependency::Simple(_), None) => {
+                    anyhow::bail!("Invalid dependency specification for {}", name)
+                }
+            };
+            
+            self.loaded.insert(name.to_string(), dep_info);
+        }
+        
+        Ok(&self.loaded[name])
+    }
+    
+    /// Load a path or git dependency from its declared source
+    async fn load_source(
+        &self,
+        name: &str,
+        declared: &DeclaredDependency,
+        detailed: &DetailedDependency,
+    ) -> Result<DependencyInfo> {
+        if let Some(path) = &declared.path {
+            self.resolver.resolve_path_dependency(name, path)
+        } else if let Some(git) = &detailed.git {
+            self.resolver.resolve_git_dependency(name, git, detailed).await
+        } else {
+            anyhow::bail!("Invalid dependency specification for {}", name)
+        }
+    }
+    
+    /// Fetch registry metadata for a package once
+    async fn registry_metadata(&mut self, name: &str) -> Result<&[VersionMetadata]> {
+        if !self.registry_versions.contains_key(name) {
+            let source = self.registry_source(name)?;
+            let versions = self.resolver.registry(&source)?.versions(name).await?;
+            self.registry_versions.insert(name.to_string(), versions);
+        }
+        
+        Ok(&self.registry_versions[name])
+    }
+    
+    /// Check whether a registry version was yanked
+    fn is_yanked(&self, name: &str, version: &Version) -> bool {
+        let version = version.to_string();
+        
+        self.registry_versions
+            .get(name)
+            .into_iter()
+            .flatten()
+            .any(|meta| meta.yanked && meta.version == version)
+    }
+    
+    /// Name a requirement that no cached version satisfies, as the likely
+    /// cause of a resolution failure without network access
+    fn explain_offline_failure(&self, error: anyhow::Error) -> anyhow::Error {
+        let mut names = self.requirements.keys().collect::<Vec<_>>();
+        names.sort();
+        
+        for name in names {
+            let is_registry = self.sources.get(name).is_some_and(|declared| {
+                declared.path.is_none() && !matches!(&declared.dependency, Dependency::Detailed(detailed) if detailed.git.is_some())
+            });
+            
+            if !is_registry {
+                continue;
+            }
+            
+            let Ok(mut cached) = self.registry_source(name).and_then(|source| self.resolver.cached_versions(&source, name)) else {
+                continue;
+            };
+            
+            if cached.is_empty() {
+                continue;
+            }
+            
+            cached.sort();
+            let unmatched = self.requirements[name]
+                .iter()
+                .find(|(requirement, _)| !cached.iter().any(|version| requirement.matches(version)));
+            
+            if let Some((requirement, declared_in)) = unmatched {
+                return error.context(format!(
+                    "No cached version of {} matches {} (required by {}); cached versions are {}. Run without --offline to download one",
+                    name,
+                    requirement,
+                    declared_in.display(),
+                    cached.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        
+        error
+    }
+    
+    /// Order registry versions by preference: a `--precise` version only,
+    /// otherwise the locked version first and the rest newest first
+    fn prefer(&self, name: &str, mut versions: Vec<Version>) -> Result<Vec<Version>> {
+        versions.sort_by(|a, b| b.cmp(a));
+        
+        if let Some(precise) = self.resolver.precise.get(name) {
+            if !versions.contains(precise) {
+                let location = if self.resolver.offline { "in the local cache" } else { "in the registry" };
+                anyhow::bail!("{} v{} is not available {}", name, precise, location);
+            }
+            
+            versions.retain(|v| v == precise);
+        }
+        
+        // Try the locked version first so unrelated changes keep it
+        if let Some(locked) = self.resolver.locked_version(name) {
+            if let Some(index) = versions.iter().position(|v| *v == locked) {
+                let locked = versions.remove(index);
+                versions.insert(0, locked);
+            }
+        }
+        
+        Ok(versions)
+    }
+}
+
+impl DependencyProvider for SourceProvider<'_> {
+    async fn versions(&mut self, name: &str) -> Result<Vec<Version>> {
+        let declared = self.source(name)?;
+        let vendored = self.vendored_directory(&declared)?.is_some();
+        
+        match &declared.dependency {
+            _ if vendored => {
+                let dep_info = self.load_local(name, &declared).await?;
+                Ok(vec![version::parse_version(&dep_info.version)?])
+            }
+            Dependency::Detailed(detailed) if detailed.path.is_some() || detailed.git.is_some() => {
+                let dep_info = self.load_local(name, &declared).await?;
+                Ok(vec![version::parse_version(&dep_info.version)?])
+            }
+            Dependency::Detailed(detailed) if detailed.version.is_none() => {
+                anyhow::bail!("Invalid dependency specification for {}", name)
+            }
+            _ if self.resolver.offline => {
+                let versions = self.resolver.cached_versions(&self.registry_source(name)?, name)?;
+                
+                if versions.is_empty() {
+                    anyhow::bail!(
+                        "Package {} is not in the local cache ({}) and network access is disabled",
+                        name, self.resolver.cache_dir.display()
+                    );
+                }
+                
+                self.prefer(name, versions)
+            }
+            _ => {
+                // Yanked versions are only used when locked or asked for
+                let pinned = [self.resolver.locked_version(name), self.resolver.precise.get(name).cloned()];
+                let versions = self.registry_metadata(name).await?
+                    .iter()
+                    .filter_map(|meta| Some((version::parse_version(&meta.version).ok()?, meta.yanked)))
+                    .filter(|(version, yanked)| !yanked || pinned.contains(&Some(version.clone())))
+                    .map(|(version, _)| version)
+                    .collect::<Vec<_>>();
+                
+                self.prefer(name, versions)
+            }
+        }
+    }
+    
+    async fn dependencies(&mut self, name: &str, version: &Version) -> Result<Vec<(String, VersionReq)>> {
+        let (dependencies, manifest_dir) = if let Some(dep_info) = self.loaded.get(name) {
+            (dep_info.manifest.dependencies.clone(), dep_info.path.clone())
+        } else if self.resolver.offline {
+            let source = self.registry_source(name)?;
+            let cache_path = self.resolver.registry_cache_path(&source, name, &version.to_string());
+            let dep_info = self.resolver.load_dependency(name, &cache_path, DependencySource::Registry)?;
+            (dep_info.manifest.dependencies, cache_path)
+        } else {
+            let version = version.to_string();
+            let dependencies = self.registry_metadata(name).await?
+                .iter()
+                .find(|meta| meta.version == version)
+                .map(|meta| meta.dependencies.clone())
+                .with_context(|| format!("Registry metadata of {} has no version {}", name, version))?;
+            let source = self.registry_source(name)?;
+            (dependencies, self.resolver.registry_cache_path(&source, name, &version))
+        };
+        
+        self.register_dependencies(&dependencies, &manifest_dir)
+    }
+}
+
+/// Dependencies of a workspace member without any
+static NO_DEPENDENCIES: BTreeSet<String> = BTreeSet::new();
+
+/// Resolved dependencies and the edges of the dependency graph
 pub struct ResolvedDependencies {
-    dependencies: HashMap<String, DependencyInfo>,
+    dependencies: BTreeMap<String, DependencyInfo>,
+    /// Direct dependencies of every workspace member
+    roots: BTreeMap<String, BTreeSet<String>>,
+    /// Direct dev-dependencies of every workspace member
+    dev_roots: BTreeMap<String, BTreeSet<String>>,
+    /// Direct dependencies of every resolved package
+    edges: BTreeMap<String, BTreeSet<String>>,
 }
 
 impl ResolvedDependencies {
     fn new() -> Self {
         Self {
-            dependencies: HashMap::new(),
+            dependencies: BTreeMap::new(),
+            roots: BTreeMap::new(),
+            dev_roots: BTreeMap::new(),
+            edges: BTreeMap::new(),
         }
     }
     
-    fn contains(&self, name: &str) -> bool {
-        self.dependencies.contains_key(name)
-    }
-    
     fn add(&mut self, name: String, info: DependencyInfo) {
+        let edges = package_names(&info.manifest.dependencies);
+        self.edges.insert(name.clone(), edges);
         self.dependencies.insert(name, info);
     }
     
-    /// Get all dependencies
-    pub fn all(&self) -> &HashMap<String, DependencyInfo> {
+    /// Get the direct dependencies of a workspace member
+    pub fn roots(&self, member: &str) -> &BTreeSet<String> {
+        self.roots.get(member).unwrap_or(&NO_DEPENDENCIES)
+    }
+    
+    /// Get the direct dev-dependencies of a workspace member
+    pub fn dev_roots(&self, member: &str) -> &BTreeSet<String> {
+        self.dev_roots.get(member).unwrap_or(&NO_DEPENDENCIES)
+    }
+    
+    /// Check whether a package is a member of the workspace
+    pub fn is_member(&self, name: &str) -> bool {
+        self.roots.contains_key(name)
+    }
+    
+    /// Find a cycle in the dependency graph.
+    ///
+    /// Walks the graph depth-first from the workspace members, then from
+    /// every other package in name order, so the reported cycle is
+    /// deterministic. Dev-dependencies are not part of the graph: they are
+    /// only built for the member's tests, so they may depend on the member.
+    ///
+    /// # Returns
+    /// The packages along the cycle, starting and ending with the same name
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut graph: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        
+        for member in self.roots.keys() {
+            graph.entry(member).or_default().extend(self.roots(member).iter().map(String::as_str));
+        }
+        
+        for (name, deps) in &self.edges {
+            graph.entry(name).or_default().extend(deps.iter().map(String::as_str));
+        }
+        
+        let mut finished = BTreeSet::new();
+        let starts = self.roots.keys()
+            .map(String::as_str)
+            .chain(graph.keys().copied())
+            .collect::<Vec<_>>();
+        
+        for start in starts {
+            let mut path = Vec::new();
+            
+            if let Some(cycle) = find_cycle_from(&graph, start, &mut path, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        
+        None
+    }
+    
+    /// Get the direct dependencies of a resolved package, ordered by name
+    pub fn dependencies_of(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.edges.get(name).into_iter().flatten().map(String::as_str)
+    }
+    
+    /// Get the resolved packages that depend directly on a package, ordered by name
+    pub fn dependents_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.edges
+            .iter()
+            .filter(move |(_, deps)| deps.contains(name))
+            .map(|(dependent, _)| dependent.as_str())
+    }
+    
+    /// Get all dependencies, ordered by name
+    pub fn all(&self) -> &BTreeMap<String, DependencyInfo> {
         &self.dependencies
     }
     
@@ -204,7 +1170,6 @@ impl ResolvedDependencies {
     ///
     /// # Returns
     /// A reference to the dependency info if found
-    #[allow(dead_code)]
     pub fn get(&self, name: &str) -> Option<&DependencyInfo> {
         self.dependencies.get(name)
     }
@@ -229,6 +1194,14 @@ pub struct DependencyInfo {
     pub manifest: Manifest,
     /// The source of the dependency
     pub source: DependencySource,
+    /// Registry URL, git URL or declared path
+    pub source_url: Option<String>,
+    /// Blake3 checksum of the registry archive or of the path/git tree
+    pub checksum: String,
+    /// Git commit that was checked out
+    pub commit: Option<String>,
+    /// Registry source name of a registry dependency
+    pub registry: Option<String>,
 }
 
 /// Dependency source indicating where a dependency comes from.
@@ -243,68 +1216,547 @@ pub enum DependencySource {
     /// Dependency from a local filesystem path
     Path,
     /// Dependency from a git repository
-    #[allow(dead_code)]
     Git,
 }
 
-/// Get cache directory
-fn get_cache_dir() -> Result<PathBuf> {
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .context("Failed to get home directory")?;
+impl DependencySource {
+    /// Get the name used for this source in Quantum.lock
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencySource::Registry => "registry",
+            DependencySource::Path => "path",
+            DependencySource::Git => "git",
+        }
+    }
+}
+
+/// Record where a dependency was declared, resolving a path dependency or
+/// a local git repository relative to the declaring manifest
+fn declare(key: &str, dep: &Dependency, manifest_dir: &Path) -> Result<DeclaredDependency> {
+    let manifest_path = manifest_dir.join("Quantum.toml");
+    let mut dep = dep.clone();
+    
+    if let Dependency::Detailed(DetailedDependency { git: Some(url), .. }) = &mut dep {
+        *url = git::resolve_url(url, manifest_dir)
+            .with_context(|| format!("Invalid git dependency {} (referenced by {})", key, manifest_path.display()))?;
+    }
     
-    Ok(PathBuf::from(home).join(".quantum").join("cache"))
+    let path = match &dep {
+        Dependency::Detailed(DetailedDependency { path: Some(path), .. }) => {
+            let joined = manifest_dir.join(path);
+            let canonical = joined.canonicalize().with_context(|| format!(
+                "Path dependency {} not found at {} (referenced by {})",
+                key, joined.display(), manifest_path.display()
+            ))?;
+            Some(canonical)
+        }
+        _ => None,
+    };
+    
+    Ok(DeclaredDependency {
+        key: key.to_string(),
+        dependency: dep,
+        path,
+        declared_in: manifest_path,
+    })
+}
+
+/// Get the package names of a dependency table, following renames
+fn package_names(dependencies: &HashMap<String, Dependency>) -> BTreeSet<String> {
+    dependencies
+        .iter()
+        .map(|(name, dep)| dep.package_name(name).to_string())
+        .collect()
 }
 
-/// Extract tar archive
-fn extract_archive(archive: &[u8], dest: &Path) -> Result<()> {
-    use std::io::Cursor;
+/// Depth-first search that returns the first back edge found as a cycle
+fn find_cycle_from<'g>(
+    graph: &BTreeMap<&'g str, BTreeSet<&'g str>>,
+    node: &'g str,
+    path: &mut Vec<&'g str>,
+    finished: &mut BTreeSet<&'g str>,
+) -> Option<Vec<String>> {
+    if let Some(index) = path.iter().position(|n| *n == node) {
+        let mut cycle = path[index..].iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        cycle.push(node.to_string());
+        return Some(cycle);
+    }
+    
+    if finished.contains(node) {
+        return None;
+    }
     
-    std::fs::create_dir_all(dest)?;
+    path.push(node);
     
-    let cursor = Cursor::new(archive);
-    let mut tar = tar::Archive::new(cursor);
+    for child in graph.get(node).into_iter().flatten() {
+        if let Some(cycle) = find_cycle_from(graph, child, path, finished) {
+            return Some(cycle);
+        }
+    }
     
-    tar.unpack(dest)?;
+    path.pop();
+    finished.insert(node);
     
-    Ok(())
+    None
 }
 
-/// Clone git repository
-fn clone_git_repo(url: &str, dest: &Path, detailed: &DetailedDependency) -> Result<()> {
-    use std::process::Command;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SourceConfig;
+    use crate::features::{FeatureSelection, ResolvedFeatures};
+    use crate::test_utils::{cache_package, git, isolated_resolver, package_archive, write_package};
+    use tempfile::TempDir;
     
-    // Clone repository
-    let mut cmd = Command::new("git");
-    cmd.arg("clone");
+    #[tokio::test]
+    async fn test_path_dependencies_relative_to_declaring_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        
+        write_package(&root, "app", "[dependencies]\na = { path = \"../libs/a\" }\nb = { path = \"../libs/./b\" }\n");
+        write_package(&temp_dir.path().join("libs/a"), "a", "[dependencies]\nb = { path = \"../b\" }\n");
+        write_package(&temp_dir.path().join("libs/b"), "b", "");
+        
+        let workspace = Workspace::load(&root).unwrap();
+        let resolved = isolated_resolver(&temp_dir.path().join("cache"))
+            .offline(true)
+            .resolve(&workspace)
+            .await
+            .unwrap();
+        
+        let b_path = temp_dir.path().join("libs/b").canonicalize().unwrap();
+        assert_eq!(resolved.all().len(), 2);
+        assert_eq!(resolved.get("b").unwrap().path, b_path);
+        assert_eq!(resolved.dependencies_of("a").collect::<Vec<_>>(), vec!["b"]);
+    }
     
-    if let Some(branch) = &detailed.branch {
-        cmd.arg("--branch").arg(branch);
+    #[tokio::test]
+    async fn test_dependency_cycle_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        
+        write_package(&root, "app", "[dependencies]\na = { path = \"../a\" }\n");
+        write_package(&temp_dir.path().join("a"), "a", "[dependencies]\nb = { path = \"../b\" }\n");
+        write_package(&temp_dir.path().join("b"), "b", "[dependencies]\nc = { path = \"../c\" }\n");
+        write_package(&temp_dir.path().join("c"), "c", "[dependencies]\na = { path = \"../a\" }\n");
+        
+        let workspace = Workspace::load(&root).unwrap();
+        let error = isolated_resolver(&temp_dir.path().join("cache"))
+            .offline(true)
+            .resolve(&workspace)
+            .await
+            .err()
+            .unwrap();
+        
+        assert_eq!(error.to_string(), "Cyclic package dependency: a -> b -> c -> a");
     }
     
-    cmd.arg(url).arg(dest);
+    #[tokio::test]
+    async fn test_dev_dependency_may_depend_on_member() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        
+        write_package(&root, "app", "[dev-dependencies]\nhelpers = { path = \"../helpers\" }\n");
+        write_package(&temp_dir.path().join("helpers"), "helpers", "[dependencies]\napp = { path = \"../app\" }\n");
+        
+        let workspace = Workspace::load(&root).unwrap();
+        let resolved = isolated_resolver(&temp_dir.path().join("cache"))
+            .offline(true)
+            .resolve(&workspace)
+            .await
+            .unwrap();
+        
+        assert_eq!(resolved.dev_roots("app"), &BTreeSet::from(["helpers".to_string()]));
+        assert_eq!(resolved.dependencies_of("helpers").collect::<Vec<_>>(), vec!["app"]);
+    }
     
-    let output = cmd.output()
-        .context("Failed to execute git clone")?;
+    #[tokio::test]
+    async fn test_workspace_members_share_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        std::fs::write(root.join("Quantum.toml"), "[workspace]\nmembers = [\"token\", \"market\"]\n").unwrap();
+        write_package(&root.join("token"), "token", "[dependencies]\nmath = { path = \"../libs/math\" }\n");
+        write_package(&root.join("market"), "market", "[dependencies]\ntoken = { path = \"../token\" }\n");
+        write_package(&root.join("libs/math"), "math", "");
+        
+        let workspace = Workspace::load(root).unwrap();
+        let resolved = isolated_resolver(&root.join("cache"))
+            .offline(true)
+            .resolve(&workspace)
+            .await
+            .unwrap();
+        
+        assert_eq!(resolved.all().keys().collect::<Vec<_>>(), vec!["math", "token"]);
+        
+        let packages = workspace.members.iter().collect::<Vec<_>>();
+        let features = ResolvedFeatures::resolve(&packages, Some(&resolved), &FeatureSelection::default(), false).unwrap();
+        assert_eq!(features.reachable("market"), BTreeSet::from(["math", "token"]));
+        assert_eq!(features.reachable("token"), BTreeSet::from(["math"]));
+    }
     
-    if !output.status.success() {
-        anyhow::bail!("Git clone failed: {}", String::from_utf8_lossy(&output.stderr));
+    #[tokio::test]
+    async fn test_renamed_dependency_uses_package_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        write_package(&root.join("app"), "app", "[dependencies]\ntokens = { path = \"../token\", package = \"token\" }\n");
+        write_package(&root.join("token"), "token", "");
+        
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let resolved = isolated_resolver(&root.join("cache")).resolve(&workspace).await.unwrap();
+        let token = resolved.get("token").unwrap();
+        assert_eq!(token.name, "token");
+        assert!(matches!(token.source, DependencySource::Path));
+        assert_eq!(resolved.roots("app"), &BTreeSet::from(["token".to_string()]));
+        
+        // Without `package`, the declared name must match the manifest
+        write_package(&root.join("app"), "app", "[dependencies]\ntokens = { path = \"../token\" }\n");
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let error = isolated_resolver(&root.join("cache")).resolve(&workspace).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("its manifest is for package token"));
+        
+        // Two names for one package must agree on its source
+        write_package(&root.join("app"), "app", "[dependencies]\ntokens = { path = \"../token\", package = \"token\" }\ncoins = { version = \"^1.0\", package = \"token\" }\n");
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let error = isolated_resolver(&root.join("cache")).resolve(&workspace).await.err().unwrap();
+        assert!(error.to_string().contains("Package token is declared from two different sources"), "{}", error);
     }
     
-    // Checkout specific ref if specified
-    if let Some(rev) = &detailed.rev {
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(dest)
-            .arg("checkout")
-            .arg(rev)
-            .output()
-            .context("Failed to checkout git revision")?;
+    #[tokio::test]
+    async fn test_registry_and_path_sources_conflict() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
         
-        if !output.status.success() {
-            anyhow::bail!("Git checkout failed: {}", String::from_utf8_lossy(&output.stderr));
+        std::fs::write(root.join("Quantum.toml"), "[workspace]\nmembers = [\"app\", \"market\"]\n").unwrap();
+        write_package(&root.join("app"), "app", "[dependencies]\ntoken = \"^1.0\"\n");
+        write_package(&root.join("market"), "market", "[dependencies]\ntoken = { path = \"../token\" }\n");
+        write_package(&root.join("token"), "token", "");
+        
+        let workspace = Workspace::load(root).unwrap();
+        let error = isolated_resolver(&root.join("cache")).offline(true).resolve(&workspace).await.err().unwrap();
+        assert!(error.to_string().contains("Package token is declared from two different sources"), "{}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_yanked_versions_are_only_used_when_locked() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_package(root, "app", "[dependencies]\na = \"^0.1\"\n");
+        let manifest = Manifest::load(root.join("Quantum.toml")).unwrap();
+        
+        let published = vec![
+            VersionMetadata { version: "0.1.0".to_string(), dependencies: HashMap::new(), yanked: false },
+            VersionMetadata { version: "0.1.1".to_string(), dependencies: HashMap::new(), yanked: true },
+        ];
+        
+        let resolver = isolated_resolver(&root.join("cache"));
+        let mut provider = SourceProvider::new(&resolver);
+        provider.register_dependencies(&manifest.dependencies, root).unwrap();
+        provider.registry_versions.insert("a".to_string(), published.clone());
+        
+        let versions = provider.versions("a").await.unwrap();
+        assert_eq!(versions, vec![version::parse_version("0.1.0").unwrap()]);
+        
+        let mut lockfile = Lockfile::new();
+        lockfile.dependencies.insert("a".to_string(), LockedDependency {
+            name: "a".to_string(),
+            version: "0.1.1".to_string(),
+            source: "registry".to_string(),
+            source_url: None,
+            checksum: None,
+            commit: None,
+        });
+        
+        let resolver = isolated_resolver(&root.join("cache")).with_lockfile(Some(lockfile));
+        let mut provider = SourceProvider::new(&resolver);
+        provider.register_dependencies(&manifest.dependencies, root).unwrap();
+        provider.registry_versions.insert("a".to_string(), published);
+        
+        let versions = provider.versions("a").await.unwrap();
+        assert_eq!(versions[0], version::parse_version("0.1.1").unwrap());
+        assert!(provider.is_yanked("a", &versions[0]));
+    }
+    
+    #[tokio::test]
+    async fn test_missing_path_dependency_names_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        
+        write_package(&root, "app", "[dependencies]\na = { path = \"../a\" }\n");
+        write_package(&temp_dir.path().join("a"), "a", "[dependencies]\nmissing = { path = \"../missing\" }\n");
+        
+        let workspace = Workspace::load(&root).unwrap();
+        let error = isolated_resolver(&temp_dir.path().join("cache"))
+            .offline(true)
+            .resolve(&workspace)
+            .await
+            .err()
+            .unwrap();
+        
+        let message = format!("{:#}", error);
+        assert!(message.contains("Path dependency missing not found"));
+        assert!(message.contains(&temp_dir.path().join("a").join("Quantum.toml").display().to_string()));
+    }
+    
+    #[tokio::test]
+    async fn test_patch_overrides_transitive_dependency() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        
+        write_package(&root, "app", "[dependencies]\na = { path = \"../a\" }\n");
+        write_package(&temp_dir.path().join("a"), "a", "[dependencies]\nb = \"^0.1\"\n");
+        write_package(&temp_dir.path().join("b-fork"), "b", "");
+        
+        let mut workspace = Workspace::load(&root).unwrap();
+        workspace.patch.insert(DEFAULT_REGISTRY_SOURCE.to_string(), HashMap::from([
+            ("b".to_string(), toml::from_str("path = \"../b-fork\"").unwrap()),
+            ("unused".to_string(), toml::from_str("path = \"../b-fork\"").unwrap()),
+        ]));
+        
+        let resolved = isolated_resolver(&temp_dir.path().join("cache"))
+            .offline(true)
+            .resolve(&workspace)
+            .await
+            .unwrap();
+        
+        let b = resolved.get("b").unwrap();
+        assert_eq!(b.path, temp_dir.path().join("b-fork").canonicalize().unwrap());
+        assert_eq!(b.source.as_str(), "path");
+        assert!(resolved.get("unused").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_registry_dependencies_resolve_from_vendor_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        let vendor_dir = root.join("vendor");
+        
+        write_package(&root, "app", "[dependencies]\na = \"^0.1\"\n");
+        write_package(&vendor_dir.join("a"), "a", "");
+        
+        PackageChecksums {
+            package: "locked".to_string(),
+            source_url: None,
+            commit: None,
+            files: checksum::file_checksums(&vendor_dir.join("a")).unwrap(),
+        }.save(&vendor_dir.join("a")).unwrap();
+        
+        let mut resolver = isolated_resolver(&temp_dir.path().join("cache")).offline(true);
+        resolver.config.source.insert(DEFAULT_REGISTRY_SOURCE.to_string(), SourceConfig {
+            replace_with: Some("vendored-sources".to_string()),
+            ..Default::default()
+        });
+        resolver.config.source.insert("vendored-sources".to_string(), SourceConfig {
+            directory: Some(vendor_dir.clone()),
+            ..Default::default()
+        });
+        
+        let workspace = Workspace::load(&root).unwrap();
+        let resolved = resolver.resolve(&workspace).await.unwrap();
+        let a = resolved.get("a").unwrap();
+        assert_eq!(a.path, vendor_dir.join("a"));
+        assert_eq!(a.checksum, "locked");
+        
+        std::fs::write(vendor_dir.join("a/src/lib.qm"), "").unwrap();
+        let error = resolver.resolve(&workspace).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("was added"));
+    }
+    
+    #[tokio::test]
+    async fn test_offline_versions_come_from_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        let resolver = isolated_resolver(&temp_dir.path().join("cache")).offline(true);
+        
+        for version in ["0.1.0", "0.1.2", "0.2.0"] {
+            cache_package(&resolver, "a", version);
+        }
+        
+        // The newest cached version matching the requirement is selected
+        write_package(&root, "app", "[dependencies]\na = \"^0.1\"\n");
+        let resolved = resolver.resolve(&Workspace::load(&root).unwrap()).await.unwrap();
+        assert_eq!(resolved.get("a").unwrap().version, "0.1.2");
+        
+        write_package(&root, "app", "[dependencies]\na = \"^0.3\"\n");
+        let error = resolver.resolve(&Workspace::load(&root).unwrap()).await.err().unwrap();
+        assert!(error.to_string().contains("No cached version of a matches ^0.3"), "{}", error);
+        assert!(error.to_string().contains("cached versions are 0.1.0, 0.1.2, 0.2.0"), "{}", error);
+        assert!(error.to_string().contains("Run without --offline"), "{}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_registry_dependencies_download_concurrently() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        let names = ["a", "b", "c"];
+        
+        // A registry serving one version of each package
+        let mut responses = HashMap::new();
+        for name in names {
+            let versions = serde_json::json!({ "versions": [{ "version": "1.0.0" }] });
+            responses.insert(format!("/api/v1/packages/{}/versions", name), versions.to_string().into_bytes());
+            responses.insert(format!("/api/v1/packages/{}/1.0.0/download", name), package_archive(name, "1.0.0"));
+        }
+        let url = serve(responses).await;
+        
+        write_package(&root, "app", "[dependencies]\na = \"^1.0\"\nb = \"^1.0\"\nc = \"^1.0\"\n");
+        let workspace = Workspace::load(&root).unwrap();
+        let resolver = DependencyResolver::with_config(Some(&url), Config::default(), temp_dir.path().join("cache")).unwrap();
+        let resolved = resolver.resolve(&workspace).await.unwrap();
+        
+        for name in names {
+            let info = resolved.get(name).unwrap();
+            assert_eq!(info.checksum, checksum::archive_checksum(&package_archive(name, "1.0.0")));
+            assert!(info.path.join("Quantum.toml").exists());
+            assert!(resolver.registry_archive_path(DEFAULT_REGISTRY_SOURCE, name, "1.0.0").exists());
         }
+        
+        // The downloaded packages are verified from the cache
+        let offline = resolver.offline(true).resolve(&workspace).await.unwrap();
+        assert_eq!(offline.get("b").unwrap().checksum, resolved.get("b").unwrap().checksum);
     }
     
-    Ok(())
+    /// Serve fixed responses by path over HTTP, one request per connection
+    async fn serve(responses: HashMap<String, Vec<u8>>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = std::sync::Arc::new(responses);
+        
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let responses = responses.clone();
+                
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => request.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+                    
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or("");
+                    let (status, body) = match responses.get(path) {
+                        Some(body) => ("200 OK", body.as_slice()),
+                        None => ("404 Not Found", &[][..]),
+                    };
+                    
+                    let header = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                    let _ = stream.write_all(header.as_bytes()).await;
+                    let _ = stream.write_all(body).await;
+                });
+            }
+        });
+        
+        url
+    }
+    
+    #[tokio::test]
+    async fn test_git_dependency_refs_and_submodules() {
+        let temp_dir = TempDir::new().unwrap();
+        let fixture = temp_dir.path();
+        let work = fixture.join("work");
+        let sub = fixture.join("sub");
+        let remote = fixture.join("lib.git");
+        let app = fixture.join("app");
+        
+        // A library with a submodule, published to a local bare repository
+        std::fs::create_dir_all(&sub).unwrap();
+        git(&sub, &["init", "--quiet", "-b", "main"]);
+        std::fs::write(sub.join("helpers.qm"), "module helpers {}\n").unwrap();
+        git(&sub, &["add", "."]);
+        git(&sub, &["commit", "--quiet", "-m", "helpers"]);
+        
+        write_package(&work, "lib", "");
+        git(&work, &["init", "--quiet", "-b", "main"]);
+        git(&work, &["submodule", "--quiet", "add", &sub.to_string_lossy(), "src/helpers"]);
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "--quiet", "-m", "v0.1.0"]);
+        git(&work, &["tag", "v0.1.0"]);
+        let first = git(&work, &["rev-parse", "HEAD"]);
+        git(fixture, &["clone", "--quiet", "--bare", &work.to_string_lossy(), &remote.to_string_lossy()]);
+        
+        let url = remote.to_string_lossy().into_owned();
+        let resolver = isolated_resolver(&fixture.join("cache"));
+        
+        // Tags are checked out with their submodules from a shallow database
+        write_package(&app, "app", &format!("[dependencies]\nlib = {{ git = \"{}\", tag = \"v0.1.0\" }}\n", url));
+        let resolved = resolver.resolve(&Workspace::load(&app).unwrap()).await.unwrap();
+        let lib = resolved.get("lib").unwrap();
+        assert_eq!(lib.commit.as_deref(), Some(first.as_str()));
+        assert!(lib.path.join("src/helpers/helpers.qm").exists());
+        assert!(lib.path.ends_with(&first[..12]));
+        assert_eq!(git(&lib.path, &["rev-parse", "--is-shallow-repository"]), "true");
+        
+        // A new commit on the branch
+        std::fs::write(
+            work.join("Quantum.toml"),
+            "[package]\nname = \"lib\"\nversion = \"0.2.0\"\n",
+        ).unwrap();
+        git(&work, &["commit", "--quiet", "-am", "v0.2.0"]);
+        git(&work, &["push", "--quiet", &url, "main"]);
+        
+        write_package(&app, "app", &format!("[dependencies]\nlib = {{ git = \"{}\", rev = \"{}\" }}\n", url, first));
+        let resolved = resolver.resolve(&Workspace::load(&app).unwrap()).await.unwrap();
+        assert_eq!(resolved.get("lib").unwrap().version, "0.1.0");
+        
+        // A locked branch keeps its commit; an unlocked one is refreshed
+        write_package(&app, "app", &format!("[dependencies]\nlib = {{ git = \"{}\", branch = \"main\" }}\n", url));
+        let workspace = Workspace::load(&app).unwrap();
+        let lockfile = Lockfile::from_resolved(&resolved);
+        
+        let locked = resolver.with_lockfile(Some(lockfile)).resolve(&workspace).await.unwrap();
+        assert_eq!(locked.get("lib").unwrap().version, "0.1.0");
+        
+        let resolver = isolated_resolver(&fixture.join("cache"));
+        let updated = resolver.resolve(&workspace).await.unwrap();
+        assert_eq!(updated.get("lib").unwrap().version, "0.2.0");
+        
+        // Offline resolution uses the database only
+        let offline = resolver.offline(true).resolve(&workspace).await.unwrap();
+        assert_eq!(offline.get("lib").unwrap().version, "0.2.0");
+        
+        // Edits to a checkout are detected the next time it is loaded
+        std::fs::write(offline.get("lib").unwrap().path.join("src/helpers/helpers.qm"), "tampered").unwrap();
+        let error = isolated_resolver(&fixture.join("cache")).offline(true).resolve(&workspace).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("Checked out files of git dependency lib were modified"));
+    }
+    
+    #[tokio::test]
+    async fn test_local_git_urls_relative_to_declaring_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let fixture = temp_dir.path();
+        let work = fixture.join("work");
+        let app = fixture.join("app");
+        
+        write_package(&work, "lib", "");
+        git(&work, &["init", "--quiet", "-b", "main"]);
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "--quiet", "-m", "lib"]);
+        git(fixture, &["clone", "--quiet", "--bare", &work.to_string_lossy(), "lib.git"]);
+        
+        // Two spellings of one repository, relative to two manifests
+        write_package(&app, "app", "[dependencies]\nlib = { git = \"../lib.git\" }\nhelper = { path = \"helper\" }\n");
+        write_package(&app.join("helper"), "helper", "[dependencies]\nlib = { git = \"../../lib.git\" }\n");
+        
+        let resolved = isolated_resolver(&fixture.join("cache"))
+            .resolve(&Workspace::load(&app).unwrap())
+            .await
+            .unwrap();
+        
+        let url = fixture.join("lib.git").canonicalize().unwrap().display().to_string();
+        let lib = resolved.get("lib").unwrap();
+        assert_eq!(lib.source_url.as_deref(), Some(url.as_str()));
+        assert!(lib.path.join("Quantum.toml").exists());
+        
+        write_package(&app, "app", "[dependencies]\nlib = { git = \"../missing.git\" }\n");
+        let error = isolated_resolver(&fixture.join("cache")).resolve(&Workspace::load(&app).unwrap()).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("Git repository ../missing.git not found"), "{:#}", error);
+    }
 }
@@ -0,0 +1,553 @@
+//! # Features
+//!
+//! Decides which features and optional dependencies are enabled for a
+//! build. Features are unified across the graph: a package needed by several
+//! dependents is built once, with every feature any of them enables.
+
+use crate::dependency::ResolvedDependencies;
+use crate::manifest::{Dependency, Manifest};
+use crate::package::Package;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Features selected on the command line
+#[derive(Debug, Clone, Default)]
+pub struct FeatureSelection {
+    /// Features enabled with `--features`
+    pub features: Vec<String>,
+    /// Enable every feature of the selected packages (`--all-features`)
+    pub all_features: bool,
+    /// Do not enable the `default` feature of the selected packages
+    pub no_default_features: bool,
+}
+
+/// Features of a package without any enabled
+static NO_FEATURES: BTreeSet<String> = BTreeSet::new();
+
+/// Features and dependencies enabled for a build
+pub struct ResolvedFeatures {
+    /// Enabled features of every package in the build
+    features: BTreeMap<String, BTreeSet<String>>,
+    /// Enabled dependencies of every package in the build
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+    /// Dev-dependencies of the selected packages, when building tests
+    dev_dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ResolvedFeatures {
+    /// Resolve the features of the selected packages and of everything
+    /// they depend on.
+    ///
+    /// Optional dependencies are always part of the resolved graph, so
+    /// Quantum.lock does not depend on the selected features; this decides
+    /// which of them are built.
+    ///
+    /// # Arguments
+    /// * `packages` - Workspace members being built
+    /// * `resolved` - Resolved dependencies of the workspace
+    /// * `selection` - Features selected on the command line
+    /// * `dev` - Also enable dev-dependencies of the selected packages
+    pub fn resolve(
+        packages: &[&Package],
+        resolved: Option<&ResolvedDependencies>,
+        selection: &FeatureSelection,
+        dev: bool,
+    ) -> Result<Self> {
+        let mut resolver = FeatureResolver {
+            manifests: HashMap::new(),
+            features: BTreeMap::new(),
+            dependencies: BTreeMap::new(),
+            dev_dependencies: BTreeMap::new(),
+        };
+        
+        for package in packages {
+            resolver.manifests.insert(package.name(), &package.manifest);
+        }
+        
+        for (name, info) in resolved.into_iter().flat_map(ResolvedDependencies::all) {
+            resolver.manifests.entry(name.as_str()).or_insert(&info.manifest);
+        }
+        
+        for feature in &selection.features {
+            if !packages.iter().any(|package| package.manifest.has_feature(feature)) {
+                anyhow::bail!("None of the selected packages has feature {}", feature);
+            }
+        }
+        
+        for package in packages {
+            let name = package.name();
+            let manifest = &package.manifest;
+            
+            resolver.activate(name, !selection.no_default_features)?;
+            
+            if dev {
+                for (dep, spec) in &manifest.dev_dependencies {
+                    resolver.enable_dependency(name, dep, spec, true)?;
+                }
+            }
+            
+            let features = if selection.all_features {
+                manifest.all_features()
+            } else {
+                selection.features
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|feature| manifest.has_feature(feature))
+                    .collect()
+            };
+            
+            for feature in features {
+                resolver.enable_feature(name, feature)?;
+            }
+        }
+        
+        Ok(Self {
+            features: resolver.features,
+            dependencies: resolver.dependencies,
+            dev_dependencies: resolver.dev_dependencies,
+        })
+    }
+    
+    /// Get the enabled features of a package
+    pub fn features(&self, name: &str) -> &BTreeSet<String> {
+        self.features.get(name).unwrap_or(&NO_FEATURES)
+    }
+    
+    /// Get every package needed to build a selected package, ordered by name
+    pub fn reachable(&self, member: &str) -> BTreeSet<&str> {
+        let mut reachable = BTreeSet::new();
+        let mut queue = [&self.dependencies, &self.dev_dependencies]
+            .into_iter()
+            .filter_map(|edges| edges.get(member))
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        
+        while let Some(name) = queue.pop() {
+            if reachable.insert(name) {
+                queue.extend(self.dependencies.get(name).into_iter().flatten().map(String::as_str));
+            }
+        }
+        
+        reachable
+    }
+}
+
+/// Walks the graph from the selected packages, enabling features
+struct FeatureResolver<'a> {
+    /// Manifests of the workspace members and resolved dependencies
+    manifests: HashMap<&'a str, &'a Manifest>,
+    /// Enabled features of every package seen so far
+    features: BTreeMap<String, BTreeSet<String>>,
+    /// Enabled dependencies; a package is activated once it has an entry
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+    dev_dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'a> FeatureResolver<'a> {
+    fn manifest(&self, name: &str) -> Result<&'a Manifest> {
+        self.manifests
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Package {} is not in the resolved dependency graph", name))
+    }
+    
+    /// Activate a package together with its non-optional dependencies
+    fn activate(&mut self, name: &str, default_features: bool) -> Result<()> {
+        let manifest = self.manifest(name)?;
+        
+        if !self.dependencies.contains_key(name) {
+            self.dependencies.insert(name.to_string(), BTreeSet::new());
+            
+            for (dep, spec) in manifest.dependencies.iter().filter(|(_, spec)| !spec.is_optional()) {
+                self.enable_dependency(name, dep, spec, false)?;
+            }
+        }
+        
+        if default_features && manifest.features.contains_key("default") {
+            self.enable_feature(name, "default")?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Enable a dependency of a package with the features it is declared with
+    ///
+    /// # Returns
+    /// The name of the dependency's package
+    fn enable_dependency<'s>(&mut self, name: &str, dep: &'s str, spec: &'s Dependency, dev: bool) -> Result<&'s str> {
+        let package = spec.package_name(dep);
+        let edges = if dev { &mut self.dev_dependencies } else { &mut self.dependencies };
+        edges.entry(name.to_string()).or_default().insert(package.to_string());
+        
+        self.activate(package, spec.default_features())?;
+        
+        for feature in spec.features() {
+            self.enable_feature(package, feature)?;
+        }
+        
+        Ok(package)
+    }
+    
+    /// Enable a feature of an activated package and everything it enables
+    fn enable_feature(&mut self, name: &str, feature: &str) -> Result<()> {
+        let manifest = self.manifest(name)?;
+        
+        if !self.features.entry(name.to_string()).or_default().insert(feature.to_string()) {
+            return Ok(());
+        }
+        
+        let Some(values) = manifest.features.get(feature) else {
+            // An optional dependency is also a feature of its own name
+            return match manifest.dependencies.get(feature).filter(|spec| spec.is_optional()) {
+                Some(spec) => self.enable_dependency(name, feature, spec, false).map(|_| ()),
+                None => anyhow::bail!("Package {} does not have feature {}", name, feature),
+            };
+        };
+        
+        for value in values {
+            let (dep, dep_feature) = match (value.strip_prefix("dep:"), value.split_once('/')) {
+                (Some(dep), _) => (dep, None),
+                (None, Some((dep, dep_feature))) => (dep, Some(dep_feature)),
+                (None, None) => {
+                    self.enable_feature(name, value)?;
+                    continue;
+                }
+            };
+            
+            let spec = manifest.dependencies.get(dep).ok_or_else(|| {
+                anyhow::anyhow!("Feature {} of {} refers to unknown dependency {}", feature, name, dep)
+            })?;
+            let package = self.enable_dependency(name, dep, spec, false)?;
+            
+            if let Some(dep_feature) = dep_feature {
+                self.enable_feature(package, dep_feature)?;
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Apply `#[cfg(...)]` attributes to Quantum source.
+///
+/// The compiler has no notion of features, so conditional compilation
+/// happens here, on the source text. The supported syntax is:
+///
+/// - predicates `feature = "name"` and `not(<predicate>)`, which may nest;
+///   any other predicate is an error rather than being left to the compiler
+/// - attributes at the start of a line, either on their own line before the
+///   item or followed by the item on the same line
+/// - several attributes stacked on consecutive lines or on one line, all of
+///   which must hold
+/// - items nested in modules or in other conditional items
+///
+/// A disabled item is removed up to its closing brace or terminating `;`.
+/// Removed lines are left empty and removed attributes are replaced by
+/// spaces, so compiler errors keep their line and column numbers.
+///
+/// # Arguments
+/// * `source` - Source code of a module
+/// * `features` - Enabled features of the package
+///
+/// # Returns
+/// The source code with disabled items removed
+pub fn apply_cfg(source: &str, features: &BTreeSet<String>) -> Result<String> {
+    let lines = source.lines().collect::<Vec<_>>();
+    let mut output = Vec::<String>::with_capacity(lines.len());
+    let mut index = 0;
+    
+    while index < lines.len() {
+        let line = lines[index];
+        let indent = line.len() - line.trim_start().len();
+        let mut column = indent;
+        let mut enabled = true;
+        
+        // Consume the attributes at the start of the line
+        while let Some((predicate, length)) = cfg_attribute(&line[column..]) {
+            enabled &= cfg_enabled(predicate, features).with_context(|| format!("Invalid attribute on line {}", index + 1))?;
+            column += length;
+            column += line[column..].len() - line[column..].trim_start().len();
+        }
+        
+        if column == indent {
+            output.push(line.to_string());
+            index += 1;
+            continue;
+        }
+        
+        if enabled {
+            let blanked = format!("{}{}{}", &line[..indent], " ".repeat(column - indent), &line[column..]);
+            output.push(blanked.trim_end().to_string());
+            index += 1;
+            continue;
+        }
+        
+        // The item starts after the attributes, or on the next line
+        let end = if line[column..].is_empty() {
+            item_end(&lines, index + 1, 0)
+        } else {
+            item_end(&lines, index, column)
+        };
+        output.extend(std::iter::repeat_n(String::new(), end - index));
+        index = end;
+    }
+    
+    Ok(output.join("\n"))
+}
+
+/// Match a `#[cfg(...)]` attribute at the start of `text`, returning its
+/// predicate and length
+fn cfg_attribute(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("#[cfg(")?;
+    let mut depth = 1;
+    
+    for (offset, c) in rest.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        
+        if depth == 0 {
+            let length = "#[cfg(".len() + offset + ")]".len();
+            return rest[offset..].starts_with(")]").then(|| (&rest[..offset], length));
+        }
+    }
+    
+    None
+}
+
+/// Evaluate a cfg predicate against the enabled features
+fn cfg_enabled(predicate: &str, features: &BTreeSet<String>) -> Result<bool> {
+    let predicate = predicate.trim();
+    
+    if let Some(inner) = predicate.strip_prefix("not(").and_then(|p| p.strip_suffix(')')) {
+        return Ok(!cfg_enabled(inner, features)?);
+    }
+    
+    let feature = predicate
+        .strip_prefix("feature")
+        .and_then(|p| p.trim_start().strip_prefix('='))
+        .and_then(|p| p.trim().strip_prefix('"'))
+        .and_then(|p| p.strip_suffix('"'))
+        .with_context(|| format!(
+            "Unsupported cfg predicate `{}`; only `feature = \"...\"` and `not(...)` are supported",
+            predicate
+        ))?;
+    
+    Ok(features.contains(feature))
+}
+
+/// Find the line after the item starting at `column` of line `start`.
+///
+/// Braces and semicolons in comments and string literals are skipped.
+fn item_end(lines: &[&str], start: usize, column: usize) -> usize {
+    let mut depth = 0usize;
+    let mut block_comment = false;
+    let mut string = false;
+    
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let line = if index == start { &line[column..] } else { line };
+        let mut chars = line.chars().peekable();
+        
+        while let Some(c) = chars.next() {
+            if block_comment {
+                if c == '*' && chars.next_if_eq(&'/').is_some() {
+                    block_comment = false;
+                }
+                continue;
+            }
+            
+            if string {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            
+            match c {
+                '/' if chars.next_if_eq(&'/').is_some() => break,
+                '/' if chars.next_if_eq(&'*').is_some() => block_comment = true,
+                '"' => string = true,
+                '{' => depth += 1,
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    
+                    if depth == 0 {
+                        return index + 1;
+                    }
+                }
+                ';' if depth == 0 => return index + 1,
+                _ => {}
+            }
+        }
+    }
+    
+    lines.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{isolated_resolver, write_package};
+    use crate::workspace::Workspace;
+    use tempfile::TempDir;
+    
+    #[tokio::test]
+    async fn test_features_unify_across_graph() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        write_package(&root.join("app"), "app", r#"
+[dependencies]
+token = { path = "../token" }
+market = { path = "../market" }
+"#);
+        write_package(&root.join("token"), "token", r#"
+[features]
+default = ["mint"]
+mint = []
+burn = []
+
+[dependencies]
+oracle = { path = "../oracle", optional = true }
+"#);
+        write_package(&root.join("market"), "market", r#"
+[features]
+pricing = ["dep:oracle", "token/burn"]
+
+[dependencies]
+token = { path = "../token", default-features = false }
+oracle = { path = "../oracle", optional = true }
+"#);
+        write_package(&root.join("oracle"), "oracle", "");
+        
+        let workspace = Workspace::load(&root.join("app")).unwrap();
+        let resolved = isolated_resolver(&root.join("cache")).resolve(&workspace).await.unwrap();
+        let packages = workspace.members.iter().collect::<Vec<_>>();
+        
+        // Optional dependencies are resolved but not built by default
+        assert!(resolved.get("oracle").is_some());
+        let features = ResolvedFeatures::resolve(&packages, Some(&resolved), &FeatureSelection::default(), false).unwrap();
+        assert_eq!(features.reachable("app"), BTreeSet::from(["market", "token"]));
+        assert_eq!(features.features("token"), &BTreeSet::from(["default".to_string(), "mint".to_string()]));
+        
+        // Features of the app's dependencies enable features across the graph
+        let mut manifest = workspace.members[0].manifest.clone();
+        manifest.features.insert("full".to_string(), vec!["market/pricing".to_string(), "token/oracle".to_string()]);
+        let app = Package { root: workspace.root.clone(), manifest };
+        
+        let selection = FeatureSelection { features: vec!["full".to_string()], ..Default::default() };
+        let features = ResolvedFeatures::resolve(&[&app], Some(&resolved), &selection, false).unwrap();
+        assert_eq!(features.reachable("app"), BTreeSet::from(["market", "oracle", "token"]));
+        assert!(features.features("token").contains("burn"));
+        assert!(features.features("token").contains("oracle"));
+        assert!(features.features("market").contains("pricing"));
+        
+        let selection = FeatureSelection { features: vec!["missing".to_string()], ..Default::default() };
+        assert!(ResolvedFeatures::resolve(&packages, Some(&resolved), &selection, false).is_err());
+    }
+    
+    #[test]
+    fn test_cfg_removes_disabled_items() {
+        let source = r#"module token {
+    #[cfg(feature = "mint")]
+    public fun mint() {
+        if (true) { }
+    }
+    
+    #[cfg(not(feature = "mint"))]
+    const MINTABLE: bool = false;
+    
+    #[cfg(feature = "burn")]
+    public fun burn() { }
+}"#;
+        let features = BTreeSet::from(["mint".to_string()]);
+        let output = apply_cfg(source, &features).unwrap();
+        
+        assert_eq!(output.lines().count(), source.lines().count());
+        assert!(output.contains("public fun mint()"));
+        assert!(!output.contains("MINTABLE"));
+        assert!(!output.contains("burn"));
+        assert!(!output.contains("#[cfg"));
+        assert!(output.trim_end().ends_with('}'));
+    }
+    
+    #[test]
+    fn test_cfg_skips_comments_and_strings() {
+        let source = r#"module token {
+    #[cfg(feature = "burn")]
+    public fun burn() {
+        // a closing } in a comment
+        let message = b"}; \" }";
+        /* { ;
+           } */
+    }
+    
+    public fun supply() { }
+}"#;
+        let output = apply_cfg(source, &BTreeSet::new()).unwrap();
+        
+        assert_eq!(output.lines().count(), source.lines().count());
+        assert!(!output.contains("burn"));
+        assert!(!output.contains("message"));
+        assert!(!output.contains("*/"));
+        assert!(output.contains("public fun supply()"));
+    }
+    
+    #[test]
+    fn test_cfg_shared_lines_and_nested_items() {
+        let source = r#"module token {
+    #[cfg(feature = "mint")] public fun mint() { }
+    #[cfg(feature = "burn")] public fun burn() {
+        supply();
+    }
+    #[cfg(not(feature = "burn"))] #[cfg(not(not(feature = "mint")))] const FIXED: bool = true;
+    
+    #[cfg(feature = "mint")]
+    module minting {
+        #[cfg(not(feature = "burn"))]
+        public fun cap() { }
+        
+        #[cfg(feature = "burn")]
+        public fun reclaim() { }
+    }
+    
+    #[cfg(feature = "burn")]
+    module burning {
+        #[cfg(feature = "mint")]
+        public fun remint() { }
+    }
+}"#;
+        let features = BTreeSet::from(["mint".to_string()]);
+        let output = apply_cfg(source, &features).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        
+        assert_eq!(lines.len(), source.lines().count());
+        assert_eq!(lines[1], "                             public fun mint() { }");
+        assert_eq!(lines[2..5], ["", "", ""]);
+        assert!(lines[5].ends_with("const FIXED: bool = true;"));
+        assert_eq!(lines[5].find("const"), source.lines().nth(5).unwrap().find("const"));
+        assert!(output.contains("module minting"));
+        assert!(output.contains("public fun cap()"));
+        assert!(!output.contains("reclaim"));
+        assert!(!output.contains("burning"));
+        assert!(!output.contains("remint"));
+        assert!(!output.contains("#[cfg"));
+        assert!(output.trim_end().ends_with('}'));
+    }
+    
+    #[test]
+    fn test_cfg_rejects_unsupported_predicates() {
+        for attribute in ["#[cfg(any(feature = \"a\"))]", "#[cfg(feature = a)]", "#[cfg(test)]"] {
+            let source = format!("module token {{\n    {}\n    public fun a() {{ }}\n}}", attribute);
+            let error = apply_cfg(&source, &BTreeSet::new()).unwrap_err();
+            assert!(format!("{:#}", error).contains("Unsupported cfg predicate"), "{:#}", error);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! # Build Fingerprints
+//!
+//! Decides which modules need to be compiled again. The fingerprint of a
+//! module covers its source, the compiler, the build profile and `[build]`
+//! settings, the enabled features of its package and the fingerprints of
+//! the packages it depends on. Fingerprints of the last build are kept in
+//! the build directory; a module is fresh when its fingerprint is unchanged
+//! and its bytecode still exists. Fingerprints and bytecode of modules that
+//! no longer exist are removed.
+
+use crate::manifest::BuildConfig;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Directory in the build directory holding the fingerprints
+pub const FINGERPRINT_DIR: &str = ".fingerprint";
+
+/// Version of the quantum-compiler crate, recorded by the build script
+const COMPILER_VERSION: &str = env!("QUANTUM_COMPILER_VERSION");
+
+/// Commit the CLI and its compiler were built from, recorded by the build
+/// script; `-dirty` when the compiler sources had uncommitted changes
+const COMPILER_COMMIT: &str = env!("QUANTUM_BUILD_COMMIT");
+
+/// Identify the compiler.
+///
+/// The compiler is linked into the CLI and built from the same repository,
+/// so the versions of both and the commit they were built from change
+/// whenever the compiler does.
+const COMPILER_ID: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "+",
+    env!("QUANTUM_COMPILER_VERSION"),
+    "+",
+    env!("QUANTUM_BUILD_COMMIT"),
+);
+
+/// Check whether a compiler version and commit identify the compiler.
+///
+/// A compiler built outside of git, or from sources with uncommitted
+/// changes, may change without its id changing, so nothing it compiled
+/// is reused.
+fn identifies_compiler(version: &str, commit: &str) -> bool {
+    version != "unknown" && commit != "unknown" && !commit.ends_with("-dirty")
+}
+
+/// Describe the profile of a build for fingerprints.
+///
+/// # Arguments
+/// * `release` - Whether the build is in release mode
+/// * `config` - The `[build]` settings of the package being built, which
+///   apply to every package in the build
+pub fn profile(release: bool, config: &BuildConfig) -> String {
+    format!(
+        "{} opt-level={} debug={} address-size={}",
+        if release { "release" } else { "debug" },
+        config.opt_level,
+        config.debug,
+        config.address_size
+    )
+}
+
+/// Compute the fingerprint of a module.
+///
+/// # Arguments
+/// * `source` - Source code of the module
+/// * `profile` - Profile of the build, from [`profile`]
+/// * `features` - Enabled features of the module's package
+/// * `dependencies` - Combined fingerprint of the package's dependencies
+pub fn module_fingerprint(source: &[u8], profile: &str, features: &BTreeSet<String>, dependencies: &str) -> String {
+    let source = blake3::hash(source).to_hex();
+    
+    combine(
+        [COMPILER_ID, profile, dependencies, source.as_str()]
+            .into_iter()
+            .chain(features.iter().map(String::as_str)),
+    )
+}
+
+/// Combine fingerprints, or any other strings, into one fingerprint
+pub fn combine<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    
+    for part in parts {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Fingerprints of the modules of one package from the last build
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Fingerprints {
+    /// File the fingerprints are stored in
+    #[serde(skip)]
+    path: PathBuf,
+    /// Fingerprint of each module, by module name
+    modules: BTreeMap<String, String>,
+    /// Whether modules compiled by an earlier build can be reused
+    #[serde(skip)]
+    reusable: bool,
+}
+
+impl Fingerprints {
+    /// Load the fingerprints of a package.
+    ///
+    /// Missing or unreadable fingerprints are treated as empty, so every
+    /// module of the package is compiled. So are all modules when the
+    /// compiler is not identified by its version and commit.
+    ///
+    /// # Arguments
+    /// * `build_dir` - The build directory
+    /// * `unit` - Name of the package's fingerprints, e.g. `deps/token`
+    pub fn load(build_dir: &Path, unit: &str) -> Self {
+        let path = build_dir.join(FINGERPRINT_DIR).join(format!("{}.json", unit));
+        
+        let modules = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Fingerprints>(&content).ok())
+            .map(|fingerprints| fingerprints.modules)
+            .unwrap_or_default();
+        
+        Self {
+            path,
+            modules,
+            reusable: identifies_compiler(COMPILER_VERSION, COMPILER_COMMIT),
+        }
+    }
+    
+    /// Check whether a module is unchanged since the last build
+    pub fn is_fresh(&self, module: &str, fingerprint: &str, output: &Path) -> bool {
+        self.reusable && self.modules.get(module).is_some_and(|stored| stored == fingerprint) && output.exists()
+    }
+    
+    /// Record the fingerprint of a module that was compiled
+    pub fn record(&mut self, module: &str, fingerprint: &str) {
+        self.modules.insert(module.to_string(), fingerprint.to_string());
+    }
+    
+    /// Save the fingerprints for the next build.
+    ///
+    /// Fingerprints of modules that are no longer part of the package are
+    /// removed first, together with their bytecode.
+    ///
+    /// # Arguments
+    /// * `modules` - Names of the package's modules in this build
+    /// * `output_dir` - Directory holding the package's bytecode
+    pub fn save(&mut self, modules: &BTreeSet<&str>, output_dir: &Path) -> Result<()> {
+        let removed = self.modules
+            .keys()
+            .filter(|module| !modules.contains(module.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        
+        for module in removed {
+            self.modules.remove(&module);
+            
+            let bytecode = output_dir.join(module).with_extension("qbc");
+            if bytecode.exists() {
+                std::fs::remove_file(&bytecode)
+                    .with_context(|| format!("Failed to remove {}", bytecode.display()))?;
+            }
+        }
+        
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_fingerprints_detect_changes() {
+        let features = BTreeSet::from(["mint".to_string()]);
+        let config = BuildConfig::default();
+        let debug = profile(false, &config);
+        let fingerprint = module_fingerprint(b"module token {}", &debug, &features, "deps");
+        
+        assert_eq!(fingerprint, module_fingerprint(b"module token {}", &debug, &features, "deps"));
+        assert_ne!(fingerprint, module_fingerprint(b"module token { }", &debug, &features, "deps"));
+        assert_ne!(fingerprint, module_fingerprint(b"module token {}", &profile(true, &config), &features, "deps"));
+        assert_ne!(fingerprint, module_fingerprint(b"module token {}", &debug, &BTreeSet::new(), "deps"));
+        assert_ne!(fingerprint, module_fingerprint(b"module token {}", &debug, &features, "changed"));
+        
+        // Every `[build]` setting is part of the profile
+        let optimized = BuildConfig { opt_level: 3, ..BuildConfig::default() };
+        let debug_info = BuildConfig { debug: true, ..BuildConfig::default() };
+        assert_ne!(debug, profile(false, &optimized));
+        assert_ne!(debug, profile(false, &debug_info));
+        
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("token.qbc");
+        std::fs::write(&output, b"bytecode").unwrap();
+        
+        let mut fingerprints = Fingerprints::load(temp_dir.path(), "deps/token");
+        fingerprints.reusable = true;
+        assert!(!fingerprints.is_fresh("token", &fingerprint, &output));
+        
+        fingerprints.record("token", &fingerprint);
+        fingerprints.record("vault", &fingerprint);
+        fingerprints.save(&BTreeSet::from(["token", "vault"]), temp_dir.path()).unwrap();
+        
+        let mut fingerprints = Fingerprints::load(temp_dir.path(), "deps/token");
+        fingerprints.reusable = true;
+        assert!(fingerprints.is_fresh("token", &fingerprint, &output));
+        
+        // Nothing is reused when the compiler is not identified
+        fingerprints.reusable = false;
+        assert!(!fingerprints.is_fresh("token", &fingerprint, &output));
+        fingerprints.reusable = true;
+        
+        // Deleted bytecode is compiled again
+        std::fs::remove_file(&output).unwrap();
+        assert!(!fingerprints.is_fresh("token", &fingerprint, &output));
+        
+        // Removed modules lose their fingerprint and bytecode
+        let vault = temp_dir.path().join("vault.qbc");
+        std::fs::write(&vault, b"bytecode").unwrap();
+        fingerprints.save(&BTreeSet::from(["token"]), temp_dir.path()).unwrap();
+        assert!(!vault.exists());
+        
+        let mut fingerprints = Fingerprints::load(temp_dir.path(), "deps/token");
+        fingerprints.reusable = true;
+        assert!(!fingerprints.is_fresh("vault", &fingerprint, &vault));
+        assert_eq!(fingerprints.modules.keys().collect::<Vec<_>>(), vec!["token"]);
+    }
+    
+    #[test]
+    fn test_unknown_or_dirty_compilers_are_not_identified() {
+        assert!(identifies_compiler("0.4.0", "0123456789abcdef"));
+        assert!(!identifies_compiler("unknown", "0123456789abcdef"));
+        assert!(!identifies_compiler("0.4.0", "unknown"));
+        assert!(!identifies_compiler("0.4.0", "0123456789abcdef-dirty"));
+    }
+}
@@ -0,0 +1,410 @@
+//! # Git Sources
+//!
+//! Fetches git dependencies with the `git` command line tool.
+//!
+//! Every repository is fetched into one bare database under `git/db` in the
+//! cache, fetching only the requested ref with `--depth 1` where possible.
+//! Each commit that is used gets its own worktree under `git/checkouts`,
+//! including its submodules, so checkouts never change once created.
+
+use crate::checksum::{self, PackageChecksums, CHECKSUM_FILE};
+use crate::manifest::DetailedDependency;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The ref a git dependency asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitReference {
+    /// The branch HEAD of the remote points to
+    DefaultBranch,
+    /// A branch, refreshed whenever the dependency is not locked
+    Branch(String),
+    /// A tag
+    Tag(String),
+    /// A commit hash or any other revision
+    Rev(String),
+}
+
+impl GitReference {
+    /// Get the reference of a git dependency
+    pub fn from_dependency(detailed: &DetailedDependency) -> Result<Self> {
+        match (&detailed.branch, &detailed.tag, &detailed.rev) {
+            (None, None, None) => Ok(Self::DefaultBranch),
+            (Some(branch), None, None) => Ok(Self::Branch(branch.clone())),
+            (None, Some(tag), None) => Ok(Self::Tag(tag.clone())),
+            (None, None, Some(rev)) => Ok(Self::Rev(rev.clone())),
+            _ => anyhow::bail!("Only one of branch, tag and rev may be specified for a git dependency"),
+        }
+    }
+    
+    /// Check whether the reference can move to another commit
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Self::DefaultBranch | Self::Branch(_))
+    }
+    
+    /// Refspec fetching the reference, or `None` for a revision
+    fn refspec(&self) -> Option<String> {
+        match self {
+            Self::DefaultBranch => Some("+HEAD:refs/remotes/origin/HEAD".to_string()),
+            Self::Branch(branch) => Some(format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch)),
+            Self::Tag(tag) => Some(format!("+refs/tags/{0}:refs/tags/{0}", tag)),
+            Self::Rev(_) => None,
+        }
+    }
+    
+    /// Name of the reference in the database
+    fn local_name(&self) -> String {
+        match self {
+            Self::DefaultBranch => "refs/remotes/origin/HEAD".to_string(),
+            Self::Branch(branch) => format!("refs/remotes/origin/{}", branch),
+            Self::Tag(tag) => format!("refs/tags/{}", tag),
+            Self::Rev(rev) => rev.clone(),
+        }
+    }
+}
+
+impl fmt::Display for GitReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DefaultBranch => write!(f, "the default branch"),
+            Self::Branch(branch) => write!(f, "branch {}", branch),
+            Self::Tag(tag) => write!(f, "tag {}", tag),
+            Self::Rev(rev) => write!(f, "revision {}", rev),
+        }
+    }
+}
+
+/// A bare repository holding everything fetched from one git URL
+pub struct GitDatabase {
+    url: String,
+    /// Directory of the bare repository
+    path: PathBuf,
+    /// Directory of the checkouts of this repository
+    checkouts: PathBuf,
+}
+
+impl GitDatabase {
+    /// Get the database of a git URL.
+    ///
+    /// # Arguments
+    /// * `git_dir` - The `git` directory of the cache
+    /// * `url` - The repository URL
+    pub fn new(git_dir: &Path, url: &str) -> Self {
+        let ident = ident(url);
+        
+        Self {
+            url: url.to_string(),
+            path: git_dir.join("db").join(&ident),
+            checkouts: git_dir.join("checkouts").join(&ident),
+        }
+    }
+    
+    /// Check whether the repository has been fetched before
+    pub fn exists(&self) -> bool {
+        self.path.join("HEAD").exists()
+    }
+    
+    /// Create the bare repository if it does not exist yet.
+    ///
+    /// Databases are fetched into without holding the cache lock, so the
+    /// repository is set up under a name private to this process and
+    /// renamed into place; if another process got there first, its
+    /// database is used instead.
+    fn init(&self) -> Result<()> {
+        if self.exists() {
+            return Ok(());
+        }
+        
+        let file_name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let partial = self.path.with_file_name(format!(".{}.{}.partial", file_name, std::process::id()));
+        
+        if partial.exists() {
+            std::fs::remove_dir_all(&partial)
+                .with_context(|| format!("Failed to remove {}", partial.display()))?;
+        }
+        
+        std::fs::create_dir_all(&partial)
+            .with_context(|| format!("Failed to create {}", partial.display()))?;
+        git(&partial, &["init", "--bare", "--quiet"])?;
+        git(&partial, &["remote", "add", "origin", &self.url])?;
+        
+        if let Err(error) = std::fs::rename(&partial, &self.path) {
+            let _ = std::fs::remove_dir_all(&partial);
+            
+            if !self.exists() {
+                return Err(error).with_context(|| format!("Failed to move {} into place", self.path.display()));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the commit a reference points to in the database
+    fn find(&self, reference: &GitReference) -> Option<String> {
+        if !self.exists() {
+            return None;
+        }
+        
+        let spec = format!("{}^{{commit}}", reference.local_name());
+        git(&self.path, &["rev-parse", "--verify", "--quiet", &spec]).ok()
+    }
+    
+    /// Check whether the database contains a commit
+    fn contains(&self, commit: &str) -> bool {
+        self.find(&GitReference::Rev(commit.to_string())).as_deref() == Some(commit)
+    }
+    
+    /// Find the commit of a reference, fetching it if needed.
+    ///
+    /// Branches are always fetched again unless `offline` is set, so an
+    /// unlocked branch dependency moves to the newest commit. Tags and
+    /// revisions are only fetched when they are not in the database yet.
+    ///
+    /// # Returns
+    /// The full commit hash
+    pub fn resolve(&self, reference: &GitReference, offline: bool) -> Result<String> {
+        let cached = self.find(reference);
+        
+        if let Some(commit) = cached.as_ref().filter(|_| offline || !reference.is_branch()) {
+            return Ok(commit.clone());
+        }
+        
+        if offline {
+            anyhow::bail!(
+                "Git dependency {} ({}) is not cached and network access is disabled",
+                self.url, reference
+            );
+        }
+        
+        self.fetch(reference)?;
+        
+        self.find(reference)
+            .with_context(|| format!("Failed to find {} in {}", reference, self.url))
+    }
+    
+    /// Make sure a locked commit is in the database, fetching it if needed
+    pub fn ensure(&self, commit: &str, reference: &GitReference, offline: bool) -> Result<()> {
+        if self.contains(commit) {
+            return Ok(());
+        }
+        
+        if offline {
+            anyhow::bail!(
+                "Commit {} of git dependency {} is not cached and network access is disabled",
+                commit, self.url
+            );
+        }
+        
+        // The locked commit is usually still the tip of the reference;
+        // otherwise ask for it directly, then for the full history
+        self.fetch(reference)?;
+        
+        if !self.contains(commit) && self.fetch_commit(commit).is_err() {
+            self.fetch_all()?;
+        }
+        
+        if !self.contains(commit) {
+            anyhow::bail!("Commit {} locked in Quantum.lock was not found in {}", commit, self.url);
+        }
+        
+        Ok(())
+    }
+    
+    /// Fetch a reference with a shallow fetch where possible
+    fn fetch(&self, reference: &GitReference) -> Result<()> {
+        self.init()?;
+        
+        let Some(refspec) = reference.refspec() else {
+            // Only full commit hashes can be fetched directly
+            let rev = reference.local_name();
+            let is_hash = rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit());
+            
+            if is_hash && self.fetch_commit(&rev).is_ok() {
+                return Ok(());
+            }
+            
+            return self.fetch_all();
+        };
+        
+        git(&self.path, &["fetch", "--quiet", "--force", "--depth", "1", "origin", &refspec])
+            .with_context(|| format!("Failed to fetch {} from {}", reference, self.url))?;
+        
+        Ok(())
+    }
+    
+    /// Fetch a single commit by its hash
+    fn fetch_commit(&self, commit: &str) -> Result<()> {
+        self.init()?;
+        git(&self.path, &["fetch", "--quiet", "--depth", "1", "origin", commit])?;
+        
+        Ok(())
+    }
+    
+    /// Fetch the full history of every branch and tag
+    fn fetch_all(&self) -> Result<()> {
+        self.init()?;
+        
+        let mut args = vec!["fetch", "--quiet", "--force"];
+        
+        if git(&self.path, &["rev-parse", "--is-shallow-repository"])? == "true" {
+            args.push("--unshallow");
+        }
+        
+        args.extend(["origin", "+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"]);
+        
+        git(&self.path, &args)
+            .with_context(|| format!("Failed to fetch {}", self.url))?;
+        
+        Ok(())
+    }
+    
+    /// Get the checkout of a commit, creating it if needed.
+    ///
+    /// Each commit is checked out once as a worktree of the database, with
+    /// its submodules, and reused from then on. The checksums of its files
+    /// are written last and mark the checkout as complete.
+    ///
+    /// # Returns
+    /// The checkout directory
+    pub fn checkout(&self, commit: &str) -> Result<PathBuf> {
+        let dest = self.checkouts.join(&commit[..commit.len().min(12)]);
+        
+        if dest.join(CHECKSUM_FILE).exists() {
+            return Ok(dest);
+        }
+        
+        // Remove a checkout that was interrupted
+        if dest.exists() {
+            std::fs::remove_dir_all(&dest)
+                .with_context(|| format!("Failed to remove {}", dest.display()))?;
+        }
+        
+        std::fs::create_dir_all(&self.checkouts)?;
+        git(&self.path, &["worktree", "prune"])?;
+        
+        let dest_str = dest.to_string_lossy();
+        git(&self.path, &["worktree", "add", "--quiet", "--detach", "--force", &dest_str, commit])
+            .with_context(|| format!("Failed to check out {} of {}", commit, self.url))?;
+        
+        if dest.join(".gitmodules").exists() {
+            // Submodules of a local repository may be local repositories too;
+            // git only allows those when asked to
+            let mut args = vec!["submodule", "update", "--quiet", "--init", "--recursive"];
+            
+            if is_local(&self.url) {
+                args.splice(0..0, ["-c", "protocol.file.allow=always"]);
+            }
+            
+            git(&dest, &args).with_context(|| format!("Failed to update submodules of {}", self.url))?;
+        }
+        
+        PackageChecksums {
+            package: checksum::tree_checksum(&dest)?,
+            source_url: Some(self.url.clone()),
+            commit: Some(commit.to_string()),
+            files: checksum::file_checksums(&dest)?,
+        }.save(&dest)?;
+        
+        Ok(dest)
+    }
+}
+
+/// Directory name for a git URL: the repository name plus a hash of the URL
+fn ident(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or("")
+        .trim_end_matches(".git");
+    let name = if name.is_empty() { "repo" } else { name };
+    let hash = blake3::hash(url.as_bytes()).to_hex();
+    
+    format!("{}-{}", name, &hash[..16])
+}
+
+/// Resolve a git URL that is a local path against the directory of the
+/// manifest declaring it, like a path dependency.
+///
+/// The path is canonicalized, so different spellings of one repository
+/// share a database; a `file://` prefix is kept. Remote URLs are returned
+/// unchanged.
+///
+/// # Arguments
+/// * `url` - The repository URL as declared
+/// * `manifest_dir` - Directory of the declaring manifest
+pub fn resolve_url(url: &str, manifest_dir: &Path) -> Result<String> {
+    if !is_local(url) {
+        return Ok(url.to_string());
+    }
+    
+    let (scheme, path) = match url.strip_prefix("file://") {
+        Some(path) => ("file://", path),
+        None => ("", url),
+    };
+    
+    let joined = manifest_dir.join(path);
+    let canonical = joined.canonicalize()
+        .with_context(|| format!("Git repository {} not found at {}", url, joined.display()))?;
+    
+    Ok(format!("{}{}", scheme, canonical.display()))
+}
+
+/// Check whether a repository URL is a path on this machine
+fn is_local(url: &str) -> bool {
+    if let Some((scheme, _)) = url.split_once("://") {
+        return scheme == "file";
+    }
+    
+    // `host:path` is the scp-like syntax of ssh
+    Path::new(url).is_absolute() || !url.split('/').next().unwrap_or(url).contains(':')
+}
+
+/// Run git in a directory and return its trimmed output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to execute git")?;
+    
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_local_urls() {
+        assert!(is_local("file:///srv/git/token.git"));
+        assert!(is_local("/srv/git/token.git"));
+        assert!(is_local("../token"));
+        assert!(!is_local("https://github.com/silverbitcoin/token.git"));
+        assert!(!is_local("ssh://git@github.com/silverbitcoin/token.git"));
+        assert!(!is_local("git@github.com:silverbitcoin/token.git"));
+    }
+    
+    #[test]
+    fn test_resolve_url() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("token.git");
+        let app = temp_dir.path().join("app");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::create_dir_all(&app).unwrap();
+        let canonical = repo.canonicalize().unwrap().display().to_string();
+        
+        assert_eq!(resolve_url("../token.git", &app).unwrap(), canonical);
+        assert_eq!(resolve_url("./../app/../token.git", &app).unwrap(), canonical);
+        assert_eq!(resolve_url(&format!("file://{}", repo.display()), &app).unwrap(), format!("file://{}", canonical));
+        assert_eq!(resolve_url("git@github.com:silverbitcoin/token.git", &app).unwrap(), "git@github.com:silverbitcoin/token.git");
+        assert!(resolve_url("../missing.git", &app).is_err());
+    }
+}
@@ -5,20 +5,20 @@
 use crate::dependency::ResolvedDependencies;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 /// Lockfile (Quantum.lock)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Lockfile {
     /// Lockfile version
     pub version: u32,
-    /// Locked dependencies
-    pub dependencies: HashMap<String, LockedDependency>,
+    /// Locked dependencies, ordered by name
+    pub dependencies: BTreeMap<String, LockedDependency>,
 }
 
 /// Locked dependency
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LockedDependency {
     /// Package name
     pub name: String,
@@ -28,8 +28,11 @@ pub struct LockedDependency {
     pub source: String,
     /// Source URL or path
     pub source_url: Option<String>,
-    /// Checksum
+    /// Blake3 checksum of the registry archive or of the path/git tree
     pub checksum: Option<String>,
+    /// Resolved git commit
+    #[serde(default)]
+    pub commit: Option<String>,
 }
 
 impl Lockfile {
@@ -37,7 +40,7 @@ impl Lockfile {
     pub fn new() -> Self {
         Self {
             version: 1,
-            dependencies: HashMap::new(),
+            dependencies: BTreeMap::new(),
         }
     }
     
@@ -50,7 +53,6 @@ impl Lockfile {
     ///
     /// # Returns
     /// The parsed lockfile
-    #[allow(dead_code)]
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = std::fs::read_to_string(path.as_ref())
             .context("Failed to read Quantum.lock")?;
@@ -61,6 +63,21 @@ impl Lockfile {
         Ok(lockfile)
     }
     
+    /// Load lockfile if one exists next to the manifest.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the Quantum.lock file
+    ///
+    /// # Returns
+    /// The parsed lockfile, or `None` if the file does not exist
+    pub fn load_if_exists<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        if !path.as_ref().exists() {
+            return Ok(None);
+        }
+        
+        Self::load(path).map(Some)
+    }
+    
     /// Save lockfile to Quantum.lock
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let content = toml::to_string_pretty(self)
@@ -77,26 +94,80 @@ impl Lockfile {
         let mut lockfile = Self::new();
         
         for (name, info) in resolved.all() {
-            let source = match info.source {
-                crate::dependency::DependencySource::Registry => "registry",
-                crate::dependency::DependencySource::Path => "path",
-                crate::dependency::DependencySource::Git => "git",
-            };
-            
             lockfile.dependencies.insert(
                 name.clone(),
                 LockedDependency {
                     name: info.name.clone(),
                     version: info.version.clone(),
-                    source: source.to_string(),
-                    source_url: None,
-                    checksum: None,
+                    source: info.source.as_str().to_string(),
+                    source_url: info.source_url.clone(),
+                    checksum: Some(info.checksum.clone()),
+                    commit: info.commit.clone(),
                 },
             );
         }
         
         lockfile
     }
+    
+    /// Compare this lockfile against a newer one.
+    ///
+    /// # Arguments
+    /// * `new` - The newer lockfile
+    ///
+    /// # Returns
+    /// Every added, removed or updated package, ordered by name
+    pub fn diff(&self, new: &Lockfile) -> Vec<LockChange> {
+        let mut changes = Vec::new();
+        
+        for (name, old_dep) in &self.dependencies {
+            match new.dependencies.get(name) {
+                None => changes.push(LockChange::Removed(old_dep.clone())),
+                Some(new_dep) if new_dep.version != old_dep.version || new_dep.commit != old_dep.commit => {
+                    changes.push(LockChange::Updated {
+                        old: old_dep.clone(),
+                        new: new_dep.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        
+        for (name, new_dep) in &new.dependencies {
+            if !self.dependencies.contains_key(name) {
+                changes.push(LockChange::Added(new_dep.clone()));
+            }
+        }
+        
+        changes.sort_by(|a, b| a.name().cmp(b.name()));
+        changes
+    }
+}
+
+impl LockChange {
+    /// Get the name of the changed package
+    pub fn name(&self) -> &str {
+        match self {
+            LockChange::Added(dep) | LockChange::Removed(dep) => &dep.name,
+            LockChange::Updated { new, .. } => &new.name,
+        }
+    }
+}
+
+/// A change between two lockfiles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockChange {
+    /// A package was added
+    Added(LockedDependency),
+    /// A package was removed
+    Removed(LockedDependency),
+    /// A package moved to a different version or commit
+    Updated {
+        /// The previously locked entry
+        old: LockedDependency,
+        /// The newly locked entry
+        new: LockedDependency,
+    },
 }
 
 impl Default for Lockfile {
@@ -104,3 +175,40 @@ impl Default for Lockfile {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn locked(name: &str, version: &str) -> LockedDependency {
+        LockedDependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: "registry".to_string(),
+            source_url: None,
+            checksum: None,
+            commit: None,
+        }
+    }
+    
+    fn lockfile(deps: &[(&str, &str)]) -> Lockfile {
+        let mut lockfile = Lockfile::new();
+        for (name, version) in deps {
+            lockfile.dependencies.insert(name.to_string(), locked(name, version));
+        }
+        lockfile
+    }
+    
+    #[test]
+    fn test_lockfile_diff() {
+        let old = lockfile(&[("a", "1.0.0"), ("b", "0.3.0"), ("c", "2.1.0")]);
+        let new = lockfile(&[("a", "1.2.0"), ("c", "2.1.0"), ("d", "0.1.0")]);
+        
+        assert_eq!(old.diff(&new), vec![
+            LockChange::Updated { old: locked("a", "1.0.0"), new: locked("a", "1.2.0") },
+            LockChange::Removed(locked("b", "0.3.0")),
+            LockChange::Added(locked("d", "0.1.0")),
+        ]);
+        assert!(new.diff(&new).is_empty());
+    }
+}
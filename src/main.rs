@@ -2,21 +2,42 @@
 //!
 //! Package manager and build tool for Quantum smart contracts.
 
+mod cache;
+mod checksum;
 mod commands;
+mod config;
 mod dependency;
+mod features;
+mod fingerprint;
+mod git;
 mod lockfile;
 mod manifest;
+mod modules;
 mod package;
+mod paths;
 mod registry;
+mod solver;
+mod version;
+mod workspace;
 
-use clap::{Parser, Subcommand};
+#[cfg(test)]
+mod test_utils;
+
+use clap::{Args, Parser, Subcommand};
 use anyhow::Result;
+use dependency::ResolveOptions;
+use features::FeatureSelection;
+use std::path::PathBuf;
+use workspace::PackageSelection;
 
 #[derive(Parser)]
 #[command(name = "quantum")]
 #[command(about = "Quantum package manager", long_about = None)]
 #[command(version)]
 struct Cli {
+    /// Directory of the package cache, instead of the one in QUANTUM_HOME
+    #[arg(long, global = true, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -39,21 +60,160 @@ enum Commands {
         /// Output directory
         #[arg(short, long)]
         output: Option<String>,
+        /// Compile every module, even if it did not change
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        lock: LockArgs,
+        #[command(flatten)]
+        packages: PackageArgs,
+        #[command(flatten)]
+        features: FeatureArgs,
     },
     /// Publish package to registry
     Publish {
         /// Skip confirmation prompt
         #[arg(short, long)]
         yes: bool,
-        /// Registry URL (defaults to official registry)
+        /// Registry name or URL (defaults to official registry)
         #[arg(long)]
         registry: Option<String>,
+        #[command(flatten)]
+        lock: LockArgs,
+        #[command(flatten)]
+        packages: PackageArgs,
     },
     /// Run tests
     Test {
         /// Filter tests by name
         filter: Option<String>,
+        #[command(flatten)]
+        lock: LockArgs,
+        #[command(flatten)]
+        packages: PackageArgs,
+        #[command(flatten)]
+        features: FeatureArgs,
+    },
+    /// Update dependencies in Quantum.lock
+    Update {
+        /// Packages to update (defaults to all)
+        packages: Vec<String>,
+        /// Update a single package to exactly this version
+        #[arg(long)]
+        precise: Option<String>,
+        /// Show what would change without writing Quantum.lock
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Display the dependency graph
+    Tree {
+        /// Show the packages that depend on the given package
+        #[arg(short, long)]
+        invert: Option<String>,
+        /// Maximum depth of the displayed graph
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Show only packages required by more than one dependent
+        #[arg(short, long)]
+        duplicates: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::tree::TreeFormat,
+    },
+    /// Copy all dependencies into the package for offline builds
+    Vendor {
+        /// Vendor directory (defaults to vendor)
+        dir: Option<String>,
+        #[command(flatten)]
+        lock: LockArgs,
     },
+    /// Stop new resolutions from selecting a published version
+    Yank {
+        /// Package version to yank, as name@version
+        package: String,
+        /// Make a yanked version available again
+        #[arg(long)]
+        undo: bool,
+        /// Registry name or URL (defaults to official registry)
+        #[arg(long)]
+        registry: Option<String>,
+    },
+    /// Inspect and clean the package cache
+    Cache {
+        #[command(subcommand)]
+        command: commands::cache::CacheCommand,
+    },
+}
+
+/// Lockfile and network flags shared by commands that resolve dependencies
+#[derive(Args)]
+struct LockArgs {
+    /// Require Quantum.lock to be up to date
+    #[arg(long)]
+    locked: bool,
+    /// Require Quantum.lock to be up to date and forbid network access
+    #[arg(long)]
+    frozen: bool,
+    /// Use only dependencies available in the local cache
+    #[arg(long)]
+    offline: bool,
+}
+
+impl LockArgs {
+    fn options(&self) -> ResolveOptions {
+        ResolveOptions {
+            locked: self.locked || self.frozen,
+            offline: self.offline || self.frozen,
+        }
+    }
+}
+
+/// Workspace member selection shared by build, test and publish
+#[derive(Args)]
+struct PackageArgs {
+    /// Workspace member to operate on (may be repeated)
+    #[arg(short = 'p', long = "package")]
+    package: Vec<String>,
+    /// Operate on every workspace member
+    #[arg(long)]
+    workspace: bool,
+}
+
+impl PackageArgs {
+    fn selection(&self) -> PackageSelection {
+        PackageSelection {
+            packages: self.package.clone(),
+            workspace: self.workspace,
+        }
+    }
+}
+
+/// Feature flags shared by build and test
+#[derive(Args)]
+struct FeatureArgs {
+    /// Features to enable, separated by commas or spaces
+    #[arg(short = 'F', long, value_delimiter = ',')]
+    features: Vec<String>,
+    /// Enable every feature of the selected packages
+    #[arg(long)]
+    all_features: bool,
+    /// Do not enable the default feature
+    #[arg(long)]
+    no_default_features: bool,
+}
+
+impl FeatureArgs {
+    fn selection(&self) -> FeatureSelection {
+        FeatureSelection {
+            features: self.features
+                .iter()
+                .flat_map(|features| features.split_whitespace())
+                .map(str::to_string)
+                .collect(),
+            all_features: self.all_features,
+            no_default_features: self.no_default_features,
+        }
+    }
 }
 
 #[tokio::main]
@@ -63,18 +223,45 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
+    if let Some(cache_dir) = &cli.cache_dir {
+        paths::set_cache_dir(cache_dir)?;
+    }
+
     match cli.command {
         Commands::New { name, here } => {
             commands::new::execute(&name, here).await?;
         }
-        Commands::Build { release, output } => {
-            commands::build::execute(release, output.as_deref()).await?;
+        Commands::Build { release, output, force, lock, packages, features } => {
+            commands::build::execute(
+                release,
+                output.as_deref(),
+                lock.options(),
+                false,
+                &packages.selection(),
+                &features.selection(),
+                force,
+            ).await?;
+        }
+        Commands::Publish { yes, registry, lock, packages } => {
+            commands::publish::execute(yes, registry.as_deref(), lock.options(), &packages.selection()).await?;
+        }
+        Commands::Test { filter, lock, packages, features } => {
+            commands::test::execute(filter.as_deref(), lock.options(), &packages.selection(), &features.selection()).await?;
+        }
+        Commands::Update { packages, precise, dry_run } => {
+            commands::update::execute(&packages, precise.as_deref(), dry_run).await?;
+        }
+        Commands::Tree { invert, depth, duplicates, format } => {
+            commands::tree::execute(invert.as_deref(), depth, duplicates, format).await?;
+        }
+        Commands::Vendor { dir, lock } => {
+            commands::vendor::execute(dir.as_deref(), lock.options()).await?;
         }
-        Commands::Publish { yes, registry } => {
-            commands::publish::execute(yes, registry.as_deref()).await?;
+        Commands::Yank { package, undo, registry } => {
+            commands::yank::execute(&package, undo, registry.as_deref()).await?;
         }
-        Commands::Test { filter } => {
-            commands::test::execute(filter.as_deref()).await?;
+        Commands::Cache { command } => {
+            commands::cache::execute(command).await?;
         }
     }
 
@@ -3,8 +3,8 @@
 //! Handles parsing and manipulation of Quantum.toml manifest files.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
 use anyhow::{Context, Result};
 
 /// Package manifest (Quantum.toml)
@@ -18,9 +18,15 @@ pub struct Manifest {
     /// Dev dependencies
     #[serde(default, rename = "dev-dependencies")]
     pub dev_dependencies: HashMap<String, Dependency>,
+    /// Features, each with the features and optional dependencies it enables
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
     /// Build configuration
     #[serde(default)]
     pub build: BuildConfig,
+    /// Overrides of packages from a source, by source and package name
+    #[serde(default)]
+    pub patch: HashMap<String, HashMap<String, Dependency>>,
 }
 
 /// Package metadata
@@ -64,6 +70,47 @@ pub enum Dependency {
     Detailed(DetailedDependency),
 }
 
+impl Dependency {
+    /// Get the version requirement, if any
+    pub fn version_requirement(&self) -> Option<&str> {
+        match self {
+            Dependency::Simple(version) => Some(version),
+            Dependency::Detailed(detailed) => detailed.version.as_deref(),
+        }
+    }
+    
+    /// Get the name of the package a dependency refers to.
+    ///
+    /// This is the dependency's key unless it is renamed with `package`.
+    pub fn package_name<'a>(&'a self, name: &'a str) -> &'a str {
+        match self {
+            Dependency::Detailed(DetailedDependency { package: Some(package), .. }) => package,
+            _ => name,
+        }
+    }
+    
+    /// Check whether the dependency is only enabled by a feature
+    pub fn is_optional(&self) -> bool {
+        matches!(self, Dependency::Detailed(detailed) if detailed.optional)
+    }
+    
+    /// Get the features to enable in the dependency
+    pub fn features(&self) -> &[String] {
+        match self {
+            Dependency::Simple(_) => &[],
+            Dependency::Detailed(detailed) => &detailed.features,
+        }
+    }
+    
+    /// Check whether the dependency's default features are enabled
+    pub fn default_features(&self) -> bool {
+        match self {
+            Dependency::Simple(_) => true,
+            Dependency::Detailed(detailed) => detailed.default_features.unwrap_or(true),
+        }
+    }
+}
+
 /// Detailed dependency specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetailedDependency {
@@ -85,13 +132,25 @@ pub struct DetailedDependency {
     /// Local path
     #[serde(default)]
     pub path: Option<String>,
-    /// Registry URL
+    /// Registry name from the `[registries]` config, or a registry URL
     #[serde(default)]
     pub registry: Option<String>,
+    /// Name of the package, when it differs from the dependency's key
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Only build the dependency when a feature enables it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// Features to enable in the dependency
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Whether to enable the dependency's `default` feature
+    #[serde(default, rename = "default-features")]
+    pub default_features: Option<bool>,
 }
 
 /// Build configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildConfig {
     /// Optimization level (0-3)
     #[serde(default = "default_opt_level")]
@@ -104,6 +163,16 @@ pub struct BuildConfig {
     pub address_size: u8,
 }
 
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            opt_level: default_opt_level(),
+            debug: false,
+            address_size: default_address_size(),
+        }
+    }
+}
+
 fn default_opt_level() -> u8 {
     2
 }
@@ -113,12 +182,24 @@ fn default_address_size() -> u8 {
 }
 
 impl Manifest {
-    /// Load manifest from Quantum.toml file
+    /// Load manifest from Quantum.toml file.
+    ///
+    /// Package fields and dependencies marked with `workspace = true` are
+    /// replaced by their values from the enclosing workspace.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = std::fs::read_to_string(path.as_ref())
             .context("Failed to read Quantum.toml")?;
         
-        let manifest: Manifest = toml::from_str(&content)
+        let mut table: toml::Table = toml::from_str(&content)
+            .context("Failed to parse Quantum.toml")?;
+        
+        if inherits_from_workspace(&table) {
+            let dir = path.as_ref().parent().unwrap_or_else(|| Path::new("."));
+            inherit_from_workspace(&mut table, dir)?;
+        }
+        
+        let manifest: Manifest = toml::Value::Table(table)
+            .try_into()
             .context("Failed to parse Quantum.toml")?;
         
         manifest.validate()?;
@@ -152,7 +233,9 @@ impl Manifest {
             },
             dependencies: HashMap::new(),
             dev_dependencies: HashMap::new(),
+            features: BTreeMap::new(),
             build: BuildConfig::default(),
+            patch: HashMap::new(),
         }
     }
     
@@ -177,6 +260,38 @@ impl Manifest {
             anyhow::bail!("Unsupported edition: {}", self.package.edition);
         }
         
+        // Validate dependency version requirements
+        for (name, dep) in self.dependencies.iter().chain(&self.dev_dependencies) {
+            if let Some(requirement) = dep.version_requirement() {
+                crate::version::parse_requirement(requirement)
+                    .with_context(|| format!("Invalid version requirement for dependency {}", name))?;
+            }
+        }
+        
+        // Validate features
+        for (name, dep) in &self.dev_dependencies {
+            if dep.is_optional() {
+                anyhow::bail!("Dev-dependency {} cannot be optional", name);
+            }
+        }
+        
+        for (feature, values) in &self.features {
+            for value in values {
+                self.validate_feature_value(feature, value)?;
+            }
+        }
+        
+        // Validate patches
+        for (source, patches) in &self.patch {
+            for (name, dep) in patches {
+                let local = matches!(dep, Dependency::Detailed(d) if d.path.is_some() || d.git.is_some());
+                
+                if !local {
+                    anyhow::bail!("Patch for {} in [patch.{}] must specify a path or git source", name, source);
+                }
+            }
+        }
+        
         // Validate build config
         if self.build.opt_level > 3 {
             anyhow::bail!("Optimization level must be 0-3");
@@ -189,26 +304,192 @@ impl Manifest {
         Ok(())
     }
     
+    /// Check that a `[features]` entry refers to a feature or dependency.
+    ///
+    /// An entry is another feature, `dep:name` for an optional dependency,
+    /// or `name/feature` for a feature of a dependency.
+    fn validate_feature_value(&self, feature: &str, value: &str) -> Result<()> {
+        if let Some(name) = value.strip_prefix("dep:") {
+            if !self.dependencies.get(name).is_some_and(Dependency::is_optional) {
+                anyhow::bail!("Feature {} enables {}, but {} is not an optional dependency", feature, value, name);
+            }
+        } else if let Some((name, _)) = value.split_once('/') {
+            if !self.dependencies.contains_key(name) {
+                anyhow::bail!("Feature {} enables {}, but {} is not a dependency", feature, value, name);
+            }
+        } else if !self.has_feature(value) {
+            anyhow::bail!("Feature {} enables unknown feature {}", feature, value);
+        }
+        
+        Ok(())
+    }
+    
+    /// Check whether the package has a feature, either from `[features]`
+    /// or implied by an optional dependency of the same name
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains_key(feature)
+            || self.dependencies.get(feature).is_some_and(Dependency::is_optional)
+    }
+    
+    /// Get every feature of the package, including those implied by
+    /// optional dependencies
+    pub fn all_features(&self) -> BTreeSet<&str> {
+        let optional = self.dependencies
+            .iter()
+            .filter(|(_, dep)| dep.is_optional())
+            .map(|(name, _)| name.as_str());
+        
+        self.features.keys().map(String::as_str).chain(optional).collect()
+    }
+    
     /// Get all dependencies (including dev dependencies).
     ///
-    /// Returns a map of all dependencies and dev dependencies combined.
+    /// Returns a map of all dependencies and dev dependencies combined. A
+    /// dependency listed in both tables keeps its normal specification, as
+    /// that is the one the package is built with.
     ///
     /// # Returns
     /// A HashMap containing all dependencies with their names as keys
-    #[allow(dead_code)]
-    pub fn all_dependencies(&self) -> HashMap<String, &Dependency> {
+    pub fn all_dependencies(&self) -> HashMap<&String, &Dependency> {
         let mut deps = HashMap::new();
         
-        for (name, dep) in &self.dependencies {
-            deps.insert(name.clone(), dep);
+        for (name, dep) in &self.dev_dependencies {
+            deps.insert(name, dep);
         }
         
-        for (name, dep) in &self.dev_dependencies {
-            deps.insert(name.clone(), dep);
+        for (name, dep) in &self.dependencies {
+            deps.insert(name, dep);
         }
         
         deps
     }
+    
+    /// Get the manifest as it is uploaded to a registry.
+    ///
+    /// Local paths mean nothing to the users of a published package, so
+    /// they are removed and dependencies are resolved by version instead.
+    /// Dev-dependencies that are only available by path are dropped.
+    pub fn for_publish(&self) -> Result<Self> {
+        let mut manifest = self.clone();
+        
+        for (name, dependency) in &mut manifest.dependencies {
+            if let Dependency::Detailed(detailed) = dependency {
+                if detailed.path.take().is_some() && detailed.version.is_none() && detailed.git.is_none() {
+                    anyhow::bail!("Path dependency {} needs a version to be published", name);
+                }
+            }
+        }
+        
+        manifest.dev_dependencies.retain(|_, dependency| match dependency {
+            Dependency::Simple(_) => true,
+            Dependency::Detailed(detailed) => {
+                detailed.path = None;
+                detailed.version.is_some() || detailed.git.is_some()
+            }
+        });
+        
+        Ok(manifest)
+    }
+}
+
+/// Dependency tables that may inherit from `[workspace.dependencies]`
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies"];
+
+/// Check whether a value is `{ workspace = true }`, possibly with more keys
+fn is_inherited(value: &toml::Value) -> bool {
+    value.get("workspace").and_then(toml::Value::as_bool) == Some(true)
+}
+
+/// Check whether a manifest inherits anything from its workspace
+fn inherits_from_workspace(table: &toml::Table) -> bool {
+    let package = table.get("package").and_then(toml::Value::as_table);
+    let dependencies = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|section| table.get(*section).and_then(toml::Value::as_table));
+    
+    package.into_iter().chain(dependencies).any(|t| t.values().any(is_inherited))
+}
+
+/// Replace inherited package fields and dependencies with the values from
+/// the nearest workspace root above `dir`.
+///
+/// Path dependencies in `[workspace.dependencies]` are relative to the
+/// workspace root and are rewritten relative to `dir`. Keys next to
+/// `workspace = true` are added to the inherited dependency, except for
+/// `features`, which are added to the inherited features.
+fn inherit_from_workspace(table: &mut toml::Table, dir: &Path) -> Result<()> {
+    let Some((root, workspace)) = crate::workspace::find_root(dir)? else {
+        anyhow::bail!("Quantum.toml inherits from a workspace, but no [workspace] was found above {}", dir.display());
+    };
+    
+    let root_manifest = root.join("Quantum.toml");
+    
+    if let Some(package) = table.get_mut("package").and_then(toml::Value::as_table_mut) {
+        for (key, value) in package.iter_mut().filter(|(_, value)| is_inherited(value)) {
+            *value = workspace.package.get(key).cloned().ok_or_else(|| anyhow::anyhow!(
+                "package.{} is inherited from the workspace, but [workspace.package] in {} does not set it",
+                key, root_manifest.display()
+            ))?;
+        }
+    }
+    
+    for section in DEPENDENCY_TABLES {
+        let Some(dependencies) = table.get_mut(*section).and_then(toml::Value::as_table_mut) else {
+            continue;
+        };
+        
+        for (name, value) in dependencies.iter_mut().filter(|(_, value)| is_inherited(value)) {
+            let dependency = workspace.dependencies.get(name).ok_or_else(|| anyhow::anyhow!(
+                "Dependency {} is inherited from the workspace, but [workspace.dependencies] in {} does not declare it",
+                name, root_manifest.display()
+            ))?;
+            
+            let mut inherited = match dependency {
+                Dependency::Simple(version) => toml::Table::from_iter([
+                    ("version".to_string(), toml::Value::String(version.clone())),
+                ]),
+                Dependency::Detailed(detailed) => toml::Table::try_from(detailed)?,
+            };
+            
+            if let Some(toml::Value::String(path)) = inherited.get_mut("path") {
+                *path = relative_to_member(&root, dir, Path::new(path)).display().to_string();
+            }
+            
+            for (key, extra) in value.as_table().into_iter().flatten() {
+                match (key.as_str(), inherited.get_mut(key), extra) {
+                    ("workspace", _, _) => {}
+                    ("features", Some(toml::Value::Array(features)), toml::Value::Array(extra)) => {
+                        for feature in extra {
+                            if !features.contains(feature) {
+                                features.push(feature.clone());
+                            }
+                        }
+                    }
+                    _ => {
+                        inherited.insert(key.clone(), extra.clone());
+                    }
+                }
+            }
+            
+            *value = toml::Value::Table(inherited);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Rewrite a path relative to the workspace root as a path relative to a
+/// member directory below it, e.g. `libs/utils` as `../libs/utils`
+fn relative_to_member(root: &Path, member: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    
+    let depth = member.strip_prefix(root)
+        .map(|relative| relative.components().filter(|c| matches!(c, Component::Normal(_))).count())
+        .unwrap_or(0);
+    
+    std::iter::repeat_n(Path::new(".."), depth).collect::<PathBuf>().join(path)
 }
 
 /// Check if version string is valid (semver)
@@ -242,4 +523,105 @@ mod tests {
         assert!(!is_valid_version("1.2.3.4"));
         assert!(!is_valid_version("abc"));
     }
+    
+    #[test]
+    fn test_all_dependencies_prefer_normal_spec() {
+        let manifest = toml::from_str::<Manifest>(r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+token = "^1.0"
+
+[dev-dependencies]
+token = { path = "../token" }
+mock = "^0.2"
+"#).unwrap();
+        
+        let deps = manifest.all_dependencies();
+        assert_eq!(deps.len(), 2);
+        assert_eq!(deps[&"token".to_string()].version_requirement(), Some("^1.0"));
+        assert_eq!(deps[&"mock".to_string()].version_requirement(), Some("^0.2"));
+    }
+    
+    #[test]
+    fn test_workspace_inheritance() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        std::fs::write(root.join("Quantum.toml"), r#"
+[workspace]
+members = ["token"]
+
+[workspace.package]
+version = "1.4.0"
+license = "Apache-2.0"
+
+[workspace.dependencies]
+math = "^2.1"
+utils = { path = "libs/utils" }
+codec = { version = "^1.0", features = ["fast"] }
+"#).unwrap();
+        std::fs::create_dir_all(root.join("token")).unwrap();
+        std::fs::write(root.join("token/Quantum.toml"), r#"
+[package]
+name = "token"
+version.workspace = true
+license = { workspace = true }
+
+[dependencies]
+math = { workspace = true }
+codec = { workspace = true, features = ["small", "fast"], optional = true }
+
+[dev-dependencies]
+utils.workspace = true
+"#).unwrap();
+        
+        let manifest = Manifest::load(root.join("token/Quantum.toml")).unwrap();
+        assert_eq!(manifest.package.version, "1.4.0");
+        assert_eq!(manifest.package.license.as_deref(), Some("Apache-2.0"));
+        assert_eq!(manifest.dependencies["math"].version_requirement(), Some("^2.1"));
+        
+        let Dependency::Detailed(utils) = &manifest.dev_dependencies["utils"] else {
+            panic!("utils should be a detailed dependency");
+        };
+        assert_eq!(utils.path.as_deref(), Some(Path::new("../libs/utils").display().to_string().as_str()));
+        
+        // Features are added to the workspace's, other keys are set
+        assert_eq!(manifest.dependencies["codec"].features(), ["fast", "small"]);
+        assert!(manifest.dependencies["codec"].is_optional());
+        
+        std::fs::write(root.join("token/Quantum.toml"), "[package]\nname = \"token\"\nversion = \"0.1.0\"\ndescription.workspace = true\n").unwrap();
+        let error = Manifest::load(root.join("token/Quantum.toml")).unwrap_err();
+        assert!(error.to_string().contains("package.description is inherited"));
+    }
+    
+    #[test]
+    fn test_publish_removes_paths() {
+        let manifest = toml::from_str::<Manifest>(r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+math = { path = "../math", version = "^2.1" }
+
+[dev-dependencies]
+mock = { path = "../mock" }
+"#).unwrap();
+        
+        let published = manifest.for_publish().unwrap();
+        let Dependency::Detailed(math) = &published.dependencies["math"] else {
+            panic!("math should be a detailed dependency");
+        };
+        assert_eq!(math.path, None);
+        assert_eq!(math.version.as_deref(), Some("^2.1"));
+        assert!(published.dev_dependencies.is_empty());
+        
+        let mut manifest = manifest;
+        manifest.dependencies = manifest.dev_dependencies.clone();
+        let error = manifest.for_publish().unwrap_err();
+        assert!(error.to_string().contains("Path dependency mock needs a version"));
+    }
 }
@@ -0,0 +1,441 @@
+//! # Module Graph
+//!
+//! Finds the modules of a package and the modules they import, so a package
+//! can be compiled together with its dependencies as one unit. Imports are
+//! read from `use` declarations; modules of the framework addresses are
+//! provided by the VM and are not part of the graph.
+
+use crate::features;
+use crate::package::Package;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Addresses whose modules are provided by the VM
+const FRAMEWORK_ADDRESSES: &[&str] = &["silver", "std"];
+
+/// Name of a module, as `package::module`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModuleId {
+    /// Package the module belongs to
+    pub package: String,
+    /// Module name within the package
+    pub name: String,
+}
+
+impl fmt::Display for ModuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.package, self.name)
+    }
+}
+
+/// A source file of a package and the module it declares
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// The declared module
+    pub id: ModuleId,
+    /// Path of the source file
+    pub path: PathBuf,
+    /// Source code with `#[cfg(feature = "...")]` applied
+    pub source: String,
+    /// Modules imported with `use`, excluding framework modules
+    pub imports: BTreeSet<ModuleId>,
+}
+
+impl Module {
+    /// Read a source file of a package.
+    ///
+    /// The module is named by its `module package::name` declaration, or by
+    /// the file name if it has none.
+    ///
+    /// # Arguments
+    /// * `package` - Name of the package the file belongs to
+    /// * `path` - Path of the source file
+    /// * `features` - Enabled features of the package
+    pub fn load(package: &str, path: PathBuf, features: &BTreeSet<String>) -> Result<Self> {
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read source file: {}", path.display()))?;
+        let source = features::apply_cfg(&source, features)
+            .with_context(|| format!("Failed to apply cfg attributes in {}", path.display()))?;
+        let code = strip_comments(&source);
+        
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let declaration = code
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("module "))
+            .map(|declaration| declaration.split(|c: char| c.is_whitespace() || c == '{').next().unwrap_or(""));
+        
+        let id = match declaration.map(|declaration| declaration.split_once("::")) {
+            Some(Some((declared, _))) if declared != package => {
+                anyhow::bail!(
+                    "{} declares a module of package {}, but belongs to package {}",
+                    path.display(), declared, package
+                );
+            }
+            Some(Some((_, name))) => module_id(package, name),
+            Some(None) => module_id(package, declaration.unwrap_or_default()),
+            None => module_id(package, &stem),
+        };
+        
+        // A `use` declaration may span several lines up to its `;`
+        let imports = code
+            .split(';')
+            .filter_map(|statement| {
+                let mut lines = statement.lines().skip_while(|line| !line.trim_start().starts_with("use "));
+                let first = lines.next()?.trim_start().strip_prefix("use ")?;
+                Some(std::iter::once(first).chain(lines).collect::<Vec<_>>().join("\n"))
+            })
+            .flat_map(|declaration| parse_use(&declaration))
+            .filter(|import| !is_framework(&import.package))
+            .collect();
+        
+        Ok(Self { id, path, source, imports })
+    }
+}
+
+/// Load every module of a package.
+///
+/// Imports of a renamed dependency, such as `tokens` for
+/// `tokens = { package = "token" }`, are recorded under the package name.
+pub fn load_package(package: &Package, features: &BTreeSet<String>) -> Result<Vec<Module>> {
+    let aliases = package.manifest
+        .all_dependencies()
+        .into_iter()
+        .map(|(key, dep)| (key.as_str(), dep.package_name(key)))
+        .filter(|(key, name)| key != name)
+        .collect::<BTreeMap<_, _>>();
+    
+    package.source_files()?
+        .into_iter()
+        .map(|path| {
+            let mut module = Module::load(package.name(), path, features)?;
+            module.imports = module.imports
+                .into_iter()
+                .map(|import| match aliases.get(import.package.as_str()) {
+                    Some(name) => module_id(name, &import.name),
+                    None => import,
+                })
+                .collect();
+            
+            Ok(module)
+        })
+        .collect()
+}
+
+/// Modules compiled together, ordered so every module comes after the
+/// modules it imports
+pub struct ModuleGraph {
+    modules: Vec<Module>,
+}
+
+impl ModuleGraph {
+    /// Order modules by their imports.
+    ///
+    /// Imports of modules outside the given set are ignored here; use
+    /// [`ModuleGraph::check_imports`] to reject them.
+    pub fn new(modules: Vec<Module>) -> Result<Self> {
+        let mut by_id = BTreeMap::new();
+        
+        for (index, module) in modules.iter().enumerate() {
+            if let Some(previous) = by_id.insert(&module.id, index) {
+                anyhow::bail!(
+                    "Module {} is declared in both {} and {}",
+                    module.id, modules[previous].path.display(), module.path.display()
+                );
+            }
+        }
+        
+        // Depth-first, visiting modules in source order
+        let mut order = Vec::with_capacity(modules.len());
+        let mut state = vec![Visit::New; modules.len()];
+        let mut path = Vec::new();
+        
+        for index in 0..modules.len() {
+            visit(&modules, &by_id, index, &mut state, &mut path, &mut order)?;
+        }
+        
+        let mut modules = modules.into_iter().map(Some).collect::<Vec<_>>();
+        let modules = order.into_iter().filter_map(|index| modules[index].take()).collect();
+        
+        Ok(Self { modules })
+    }
+    
+    /// Check that every import names a module in the graph.
+    ///
+    /// # Arguments
+    /// * `packages` - Packages whose modules are in the graph
+    pub fn check_imports(&self, packages: &BTreeSet<&str>) -> Result<()> {
+        let ids = self.modules.iter().map(|module| &module.id).collect::<BTreeSet<_>>();
+        
+        for module in &self.modules {
+            for import in &module.imports {
+                if ids.contains(import) {
+                    continue;
+                }
+                
+                if packages.contains(import.package.as_str()) {
+                    anyhow::bail!(
+                        "Unresolved import {} in {}: package {} has no module {}",
+                        import, module.path.display(), import.package, import.name
+                    );
+                }
+                
+                anyhow::bail!(
+                    "Unresolved import {} in {}: {} is not an enabled dependency of {}",
+                    import, module.path.display(), import.package, module.id.package
+                );
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Get the modules in dependency order
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+    
+    /// Take the modules in dependency order
+    pub fn into_modules(self) -> Vec<Module> {
+        self.modules
+    }
+}
+
+/// Progress of the depth-first ordering
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+fn visit(
+    modules: &[Module],
+    by_id: &BTreeMap<&ModuleId, usize>,
+    index: usize,
+    state: &mut [Visit],
+    path: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<()> {
+    match state[index] {
+        Visit::Done => return Ok(()),
+        Visit::InProgress => {
+            let start = path.iter().position(|&i| i == index).unwrap_or(0);
+            let cycle = path[start..]
+                .iter()
+                .chain([&index])
+                .map(|&i| modules[i].id.to_string())
+                .collect::<Vec<_>>();
+            anyhow::bail!("Cyclic module dependency: {}", cycle.join(" -> "));
+        }
+        Visit::New => {}
+    }
+    
+    state[index] = Visit::InProgress;
+    path.push(index);
+    
+    for import in &modules[index].imports {
+        if let Some(&dependency) = by_id.get(import) {
+            visit(modules, by_id, dependency, state, path, order)?;
+        }
+    }
+    
+    path.pop();
+    state[index] = Visit::Done;
+    order.push(index);
+    
+    Ok(())
+}
+
+/// Get the modules named by a `use` declaration, without the `use` keyword
+fn parse_use(declaration: &str) -> Vec<ModuleId> {
+    let declaration = declaration.trim().trim_end_matches(';').trim();
+    
+    let Some((package, rest)) = declaration.split_once("::") else {
+        return Vec::new();
+    };
+    
+    // `use package::{a, b::Item}` imports several modules
+    let items = match rest.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+        Some(list) => list.split(',').collect(),
+        None => vec![rest],
+    };
+    
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let name = item.split("::").next()?.split_whitespace().next()?;
+            (name != "Self").then(|| module_id(package.trim(), name))
+        })
+        .collect()
+}
+
+/// Remove `//` and `/* */` comments from source code, keeping string
+/// literals and line breaks
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                while let Some(c) = chars.next() {
+                    if c == '\n' {
+                        output.push(c);
+                    } else if c == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                output.push(c);
+                
+                while let Some(c) = chars.next() {
+                    output.push(c);
+                    
+                    match c {
+                        '\\' => output.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+    
+    output
+}
+
+fn module_id(package: &str, name: &str) -> ModuleId {
+    ModuleId {
+        package: package.to_string(),
+        name: name.to_string(),
+    }
+}
+
+/// Check whether an address belongs to the framework or is a numeric address
+fn is_framework(address: &str) -> bool {
+    FRAMEWORK_ADDRESSES.contains(&address) || address.starts_with("0x")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::write_package;
+    use tempfile::TempDir;
+    
+    fn write_module(dir: &std::path::Path, file: &str, source: &str) -> PathBuf {
+        let path = dir.join(file);
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+    
+    #[test]
+    fn test_module_graph_orders_imports() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let features = BTreeSet::from(["oracle".to_string()]);
+        
+        let market = write_module(dir, "market.qm", r#"module app::market {
+    use silver::object::{Self, UID};
+    use app::pricing;
+    use token::{coin, vault::Vault};
+    #[cfg(feature = "oracle")]
+    use oracle::feed;
+}"#);
+        let pricing = write_module(dir, "pricing.qm", "module app::pricing {\n    use 0x2::math;\n}\n");
+        let coin = write_module(dir, "coin.qm", "module token::coin {}\n");
+        let vault = write_module(dir, "vault.qm", "module token::vault {\n    use token::coin as c;\n}\n");
+        
+        let market = Module::load("app", market, &features).unwrap();
+        assert_eq!(market.id.to_string(), "app::market");
+        assert_eq!(
+            market.imports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["app::pricing", "oracle::feed", "token::coin", "token::vault"]
+        );
+        
+        let modules = vec![
+            market,
+            Module::load("app", pricing, &features).unwrap(),
+            Module::load("token", vault, &features).unwrap(),
+            Module::load("token", coin.clone(), &features).unwrap(),
+        ];
+        let graph = ModuleGraph::new(modules.clone()).unwrap();
+        
+        // The oracle dependency is not part of the build
+        let error = graph.check_imports(&BTreeSet::from(["app", "token"])).unwrap_err();
+        assert!(error.to_string().contains("oracle is not an enabled dependency of app"), "{}", error);
+        
+        let order = graph.into_modules().into_iter().map(|module| module.id.to_string()).collect::<Vec<_>>();
+        assert_eq!(order, vec!["app::pricing", "token::coin", "token::vault", "app::market"]);
+        
+        let oracle = write_module(dir, "feed.qm", "module oracle::feed {\n    use app::market;\n}\n");
+        let mut cyclic = modules.clone();
+        cyclic.push(Module::load("oracle", oracle, &features).unwrap());
+        let error = ModuleGraph::new(cyclic).err().unwrap();
+        assert!(error.to_string().contains("app::market -> oracle::feed -> app::market"), "{}", error);
+        
+        let mut duplicate = modules;
+        duplicate.push(Module::load("token", coin, &features).unwrap());
+        assert!(ModuleGraph::new(duplicate).is_err());
+    }
+    
+    #[test]
+    fn test_module_declarations() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let features = BTreeSet::new();
+        
+        let vault = write_module(dir, "vault.qm", r#"module token::vault {
+    // use legacy::coin;
+    use token::{
+        coin,
+        supply /* , minter */
+    };
+    /* use legacy::vault;
+       use legacy::bank; */
+    const URL: vector<u8> = b"https://example.com/*";
+    use math::fixed;
+}"#);
+        let vault = Module::load("token", vault, &features).unwrap();
+        assert_eq!(
+            vault.imports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["math::fixed", "token::coin", "token::supply"]
+        );
+        
+        let coin = write_module(dir, "coin.qm", "module market::coin {}\n");
+        let error = Module::load("token", coin, &features).unwrap_err();
+        assert!(error.to_string().contains("declares a module of package market, but belongs to package token"), "{}", error);
+        
+        let math = write_module(dir, "fixed.qm", "module math::fixed {\n}");
+        let math = Module::load("math", math, &features).unwrap();
+        assert_eq!(math.id.to_string(), "math::fixed");
+        
+        // Modules keep their own source, so compiler errors refer to their lines
+        let graph = ModuleGraph::new(vec![vault, math]).unwrap();
+        let paths = graph.modules().iter().map(|module| module.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths, vec![dir.join("fixed.qm"), dir.join("vault.qm")]);
+        assert_eq!(graph.modules()[0].source, "module math::fixed {\n}");
+    }
+    
+    #[test]
+    fn test_renamed_dependency_imports() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        write_package(root, "app", "[dependencies]\ntokens = { path = \"../token\", package = \"token\" }\n");
+        write_module(&root.join("src"), "main.qm", "module app::main {\n    use tokens::coin;\n}\n");
+        
+        let package = Package::load(root).unwrap();
+        let modules = load_package(&package, &BTreeSet::new()).unwrap();
+        assert_eq!(
+            modules[0].imports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["token::coin"]
+        );
+    }
+}
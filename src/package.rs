@@ -29,28 +29,11 @@ impl Package {
         Ok(Self { root, manifest })
     }
     
-    /// Load package from current directory
-    pub fn load_current() -> Result<Self> {
-        let current_dir = std::env::current_dir()
-            .context("Failed to get current directory")?;
-        
-        Self::load(current_dir)
-    }
-    
     /// Get source directory
     pub fn src_dir(&self) -> PathBuf {
         self.root.join("src")
     }
     
-    /// Get build directory
-    pub fn build_dir(&self, release: bool) -> PathBuf {
-        if release {
-            self.root.join("build").join("release")
-        } else {
-            self.root.join("build").join("debug")
-        }
-    }
-    
     /// Get all source files
     pub fn source_files(&self) -> Result<Vec<PathBuf>> {
         let src_dir = self.src_dir();
@@ -0,0 +1,156 @@
+//! # Quantum Directories
+//!
+//! Where the user configuration and the package cache live. Every
+//! component finds them through this module, in order of precedence:
+//!
+//! 1. `--cache-dir`, for the cache only
+//! 2. `$QUANTUM_HOME`, holding `config.toml` and `cache/`
+//! 3. `~/.quantum`, if it exists or on other platforms
+//! 4. `$XDG_CONFIG_HOME/quantum` and `$XDG_CACHE_HOME/quantum` on Linux
+//! 5. `~/.config/quantum` and `~/.cache/quantum` on Linux
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable overriding the Quantum home directory
+pub const QUANTUM_HOME: &str = "QUANTUM_HOME";
+
+/// Cache directory given with `--cache-dir`
+static CACHE_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// A directory Quantum keeps files in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Cache,
+    Config,
+}
+
+impl Kind {
+    /// XDG variable of the directory
+    fn xdg_var(self) -> &'static str {
+        match self {
+            Kind::Cache => "XDG_CACHE_HOME",
+            Kind::Config => "XDG_CONFIG_HOME",
+        }
+    }
+    
+    /// Default of the XDG variable, relative to the home directory
+    fn xdg_default(self) -> &'static str {
+        match self {
+            Kind::Cache => ".cache",
+            Kind::Config => ".config",
+        }
+    }
+    
+    /// The directory inside a Quantum home directory
+    fn in_home(self, home: &Path) -> PathBuf {
+        match self {
+            Kind::Cache => home.join("cache"),
+            Kind::Config => home.to_path_buf(),
+        }
+    }
+}
+
+/// Use a cache directory given on the command line instead of the default.
+///
+/// # Arguments
+/// * `dir` - The cache directory, relative to the current directory
+pub fn set_cache_dir(dir: &Path) -> Result<()> {
+    let dir = std::path::absolute(dir)?;
+    
+    if CACHE_DIR_OVERRIDE.set(dir).is_err() {
+        anyhow::bail!("The cache directory was already set");
+    }
+    
+    Ok(())
+}
+
+/// Get the package cache directory
+pub fn cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = CACHE_DIR_OVERRIDE.get() {
+        return Ok(dir.clone());
+    }
+    
+    resolve(Kind::Cache, env_path)
+}
+
+/// Get the path of the user configuration file
+pub fn config_file() -> Result<PathBuf> {
+    Ok(resolve(Kind::Config, env_path)?.join("config.toml"))
+}
+
+/// Resolve a directory from environment variables.
+///
+/// # Arguments
+/// * `kind` - The directory to resolve
+/// * `var` - Lookup of an environment variable, `None` when unset or empty
+fn resolve(kind: Kind, var: impl Fn(&str) -> Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(home) = var(QUANTUM_HOME) {
+        return Ok(kind.in_home(&std::path::absolute(home)?));
+    }
+    
+    let linux = cfg!(target_os = "linux");
+    let home = var("HOME").or_else(|| var("USERPROFILE"));
+    
+    // An existing ~/.quantum keeps being used, wherever XDG points
+    if let Some(legacy) = home.as_ref().map(|home| home.join(".quantum")) {
+        if !linux || legacy.exists() {
+            return Ok(kind.in_home(&legacy));
+        }
+    }
+    
+    // Relative XDG paths are invalid and ignored
+    if let Some(dir) = var(kind.xdg_var()).filter(|dir| linux && dir.is_absolute()) {
+        return Ok(dir.join("quantum"));
+    }
+    
+    let Some(home) = home else {
+        let hint = if kind == Kind::Cache { " or pass --cache-dir" } else { "" };
+        anyhow::bail!("Could not determine the home directory; set {}{}", QUANTUM_HOME, hint);
+    };
+    
+    Ok(home.join(kind.xdg_default()).join("quantum"))
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_directory_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let home = temp_dir.path().to_path_buf();
+        let resolve_with = |kind, vars: &[(&str, PathBuf)]| {
+            let vars = vars.iter().cloned().collect::<HashMap<_, _>>();
+            resolve(kind, |name| vars.get(name).cloned())
+        };
+        
+        // QUANTUM_HOME wins over everything else
+        let quantum_home = home.join("volume");
+        let vars = [(QUANTUM_HOME, quantum_home.clone()), ("XDG_CACHE_HOME", home.join("xdg")), ("HOME", home.clone())];
+        assert_eq!(resolve_with(Kind::Cache, &vars).unwrap(), quantum_home.join("cache"));
+        assert_eq!(resolve_with(Kind::Config, &vars).unwrap(), quantum_home);
+        
+        // Without a home directory there is nothing to fall back to
+        assert!(resolve_with(Kind::Cache, &[]).is_err());
+        
+        if cfg!(target_os = "linux") {
+            let vars = [("XDG_CACHE_HOME", home.join("xdg")), ("XDG_CONFIG_HOME", PathBuf::from("relative")), ("HOME", home.clone())];
+            assert_eq!(resolve_with(Kind::Cache, &vars).unwrap(), home.join("xdg/quantum"));
+            assert_eq!(resolve_with(Kind::Config, &vars).unwrap(), home.join(".config/quantum"));
+        }
+        
+        // An existing ~/.quantum keeps being used, even with XDG variables set
+        std::fs::create_dir(home.join(".quantum")).unwrap();
+        let vars = [("XDG_CACHE_HOME", home.join("xdg")), ("XDG_CONFIG_HOME", home.join("xdg-config")), ("HOME", home.clone())];
+        assert_eq!(resolve_with(Kind::Cache, &vars).unwrap(), home.join(".quantum/cache"));
+        assert_eq!(resolve_with(Kind::Config, &vars).unwrap(), home.join(".quantum"));
+    }
+}
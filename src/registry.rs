@@ -2,15 +2,19 @@
 //!
 //! Client for interacting with the Quantum package registry.
 
+use crate::manifest::Dependency;
 use crate::package::Package;
 use anyhow::{Context, Result};
+use indicatif::ProgressBar;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use base64::Engine;
 
 /// Default registry URL
 const DEFAULT_REGISTRY_URL: &str = "https://registry.silverbitcoin.org";
 
 /// Package registry client
+#[derive(Clone)]
 pub struct Registry {
     url: String,
     client: reqwest::Client,
@@ -45,6 +49,7 @@ impl Registry {
             description: package.manifest.package.description.clone(),
             license: package.manifest.package.license.clone(),
             repository: package.manifest.package.repository.clone(),
+            dependencies: package.manifest.dependencies.clone(),
             archive_data: base64::engine::general_purpose::STANDARD.encode(&archive),
         };
         
@@ -63,11 +68,16 @@ impl Registry {
         Ok(())
     }
 
-    /// Download a package from the registry
-    pub async fn download(&self, name: &str, version: &str) -> Result<Vec<u8>> {
+    /// Download a package from the registry.
+    ///
+    /// # Arguments
+    /// * `name` - The package name
+    /// * `version` - The exact version to download
+    /// * `progress` - Progress bar advanced by the number of bytes received
+    pub async fn download(&self, name: &str, version: &str, progress: &ProgressBar) -> Result<Vec<u8>> {
         let download_url = format!("{}/api/v1/packages/{}/{}/download", self.url, name, version);
         
-        let response = self.client
+        let mut response = self.client
             .get(&download_url)
             .send()
             .await
@@ -77,11 +87,73 @@ impl Registry {
             anyhow::bail!("Package not found: {} v{}", name, version);
         }
         
-        let archive = response.bytes().await?.to_vec();
+        if let Some(length) = response.content_length() {
+            progress.set_length(length);
+        }
+        
+        let mut archive = Vec::new();
+        
+        while let Some(chunk) = response.chunk().await.context("Failed to download package")? {
+            progress.inc(chunk.len() as u64);
+            archive.extend_from_slice(&chunk);
+        }
         
         Ok(archive)
     }
     
+    /// Yank a published version, or undo a previous yank.
+    ///
+    /// A yanked version stays downloadable for lockfiles that pin it, but
+    /// is no longer selected by new resolutions.
+    ///
+    /// # Arguments
+    /// * `name` - The package name
+    /// * `version` - The exact version to yank
+    /// * `undo` - Make the version available again
+    pub async fn yank(&self, name: &str, version: &str, undo: bool) -> Result<()> {
+        let action = if undo { "unyank" } else { "yank" };
+        let yank_url = format!("{}/api/v1/packages/{}/{}/{}", self.url, name, version, action);
+        
+        let response = self.client
+            .put(&yank_url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send {} request", action))?;
+        
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            anyhow::bail!("Registry error: {}", error_text);
+        }
+        
+        Ok(())
+    }
+    
+    /// List the published versions of a package.
+    ///
+    /// # Arguments
+    /// * `name` - The package name
+    ///
+    /// # Returns
+    /// Metadata for every version the registry knows about
+    pub async fn versions(&self, name: &str) -> Result<Vec<VersionMetadata>> {
+        let versions_url = format!("{}/api/v1/packages/{}/versions", self.url, name);
+        
+        let response = self.client
+            .get(&versions_url)
+            .send()
+            .await
+            .context("Failed to fetch package versions")?;
+        
+        if !response.status().is_success() {
+            anyhow::bail!("Package not found: {}", name);
+        }
+        
+        let results: VersionsResponse = response.json().await
+            .context("Failed to parse package versions")?;
+        
+        Ok(results.versions)
+    }
+    
     /// Search for packages in the registry.
     ///
     /// Queries the registry for packages matching the search term.
@@ -119,6 +191,7 @@ struct PublishRequest {
     description: Option<String>,
     license: Option<String>,
     repository: Option<String>,
+    dependencies: HashMap<String, Dependency>,
     archive_data: String,
 }
 
@@ -144,6 +217,26 @@ pub struct PackageInfo {
     pub downloads: u64,
 }
 
+/// Metadata about a single published version of a package.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionMetadata {
+    /// The published version
+    pub version: String,
+    /// Dependencies declared by this version
+    #[serde(default)]
+    pub dependencies: HashMap<String, Dependency>,
+    /// Whether the version was yanked
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+/// Versions response from the registry.
+#[derive(Debug, Deserialize)]
+struct VersionsResponse {
+    /// All published versions of the package
+    versions: Vec<VersionMetadata>,
+}
+
 /// Search response from the registry.
 ///
 /// Contains the list of packages matching a search query.
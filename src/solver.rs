@@ -0,0 +1,875 @@
+//! # Version Solver
+//!
+//! Version selection based on PubGrub.
+//!
+//! The solver keeps a list of incompatibilities: sets of terms that must
+//! not all hold at once, such as "a 1.0.0 is selected and c is not ^2".
+//! Packages are decided one at a time in name order, most preferred
+//! compatible version first, and after every decision unit propagation
+//! derives what the incompatibilities imply for the other packages.
+//!
+//! When a conflict is found, the solver derives a new incompatibility that
+//! captures its cause, learns it so the same conflict is never explored
+//! again, and jumps back to the decision that made it possible. Every
+//! derived incompatibility remembers the two it was derived from, so a
+//! failure is explained as a chain of "because x depends on y" steps that
+//! ends at the root requirements.
+//!
+//! Version sets are represented by the versions the provider offers, which
+//! are the only versions that can ever be selected.
+
+use crate::version::{Version, VersionReq};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Source of package versions and their dependencies
+pub trait DependencyProvider {
+    /// Get all versions of a package, most preferred first
+    async fn versions(&mut self, name: &str) -> Result<Vec<Version>>;
+    
+    /// Get the dependencies of a specific package version
+    async fn dependencies(&mut self, name: &str, version: &Version) -> Result<Vec<(String, VersionReq)>>;
+}
+
+/// Internal name of the root package; no real package has an empty name
+const ROOT: &str = "";
+
+/// A statement about the selected version of one package.
+///
+/// A positive term holds when one of its versions is selected. A negative
+/// term holds when any other version, or no version at all, is selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    positive: bool,
+    versions: BTreeSet<Version>,
+}
+
+impl Term {
+    fn positive(versions: BTreeSet<Version>) -> Self {
+        Self { positive: true, versions }
+    }
+    
+    fn negative(versions: BTreeSet<Version>) -> Self {
+        Self { positive: false, versions }
+    }
+    
+    fn negate(&self) -> Self {
+        Self { positive: !self.positive, versions: self.versions.clone() }
+    }
+    
+    fn intersect(&self, other: &Term) -> Term {
+        match (self.positive, other.positive) {
+            (true, true) => Term::positive(self.versions.intersection(&other.versions).cloned().collect()),
+            (true, false) => Term::positive(self.versions.difference(&other.versions).cloned().collect()),
+            (false, true) => Term::positive(other.versions.difference(&self.versions).cloned().collect()),
+            (false, false) => Term::negative(self.versions.union(&other.versions).cloned().collect()),
+        }
+    }
+    
+    /// Check whether the term can never hold
+    fn is_empty(&self) -> bool {
+        self.positive && self.versions.is_empty()
+    }
+    
+    /// Check whether the term always holds
+    fn is_any(&self) -> bool {
+        !self.positive && self.versions.is_empty()
+    }
+    
+    /// Check whether `other` holds whenever this term holds
+    fn satisfies(&self, other: &Term) -> bool {
+        self.intersect(&other.negate()).is_empty()
+    }
+    
+    /// Check whether this term and `other` can never hold together
+    fn is_disjoint(&self, other: &Term) -> bool {
+        self.intersect(other).is_empty()
+    }
+}
+
+/// Why an incompatibility holds
+#[derive(Debug, Clone)]
+enum Cause {
+    /// The root package must be selected
+    Root,
+    /// A package version depends on another package
+    Dependency {
+        package: String,
+        version: Version,
+        dependency: String,
+        requirement: VersionReq,
+    },
+    /// Derived from two other incompatibilities while resolving a conflict
+    Derived(usize, usize),
+}
+
+/// Terms that must not all hold at once, by package name
+#[derive(Debug, Clone)]
+struct Incompatibility {
+    terms: BTreeMap<String, Term>,
+    cause: Cause,
+}
+
+/// A decision or derivation in the partial solution
+#[derive(Debug, Clone)]
+struct Assignment {
+    package: String,
+    term: Term,
+    decision_level: usize,
+    /// Incompatibility the term was derived from; `None` for decisions
+    cause: Option<usize>,
+}
+
+/// Relation between the partial solution and a term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relation {
+    Satisfied,
+    Contradicted,
+    Inconclusive,
+}
+
+/// What the partial solution implies for an incompatibility
+enum Implication {
+    /// Every term holds
+    Conflict,
+    /// Every term but the one for this package holds, so it must not
+    Derive(String),
+    /// Nothing follows yet
+    Nothing,
+}
+
+/// Version solver
+pub struct Solver<'a, P> {
+    provider: &'a mut P,
+    root: String,
+    root_requirements: Vec<(String, VersionReq)>,
+    versions: HashMap<String, Vec<Version>>,
+    incompatibilities: Vec<Incompatibility>,
+    by_package: HashMap<String, Vec<usize>>,
+    assignments: Vec<Assignment>,
+}
+
+impl<'a, P: DependencyProvider> Solver<'a, P> {
+    /// Create a new solver.
+    ///
+    /// # Arguments
+    /// * `provider` - Source of versions and dependencies
+    /// * `root` - Name of the root package, used in conflict explanations
+    pub fn new(provider: &'a mut P, root: &str) -> Self {
+        Self {
+            provider,
+            root: root.to_string(),
+            root_requirements: Vec::new(),
+            versions: HashMap::new(),
+            incompatibilities: Vec::new(),
+            by_package: HashMap::new(),
+            assignments: Vec::new(),
+        }
+    }
+    
+    /// Select one version of every package reachable from the root requirements.
+    ///
+    /// # Arguments
+    /// * `root` - Requirements of the root package
+    ///
+    /// # Returns
+    /// The selected version of every package, ordered by name
+    pub async fn solve(mut self, root: Vec<(String, VersionReq)>) -> Result<BTreeMap<String, Version>> {
+        self.root_requirements = root;
+        
+        let root_version = BTreeSet::from([Version::new(0, 0, 0)]);
+        self.versions.insert(ROOT.to_string(), root_version.iter().cloned().collect());
+        self.add_incompatibility(Incompatibility {
+            terms: BTreeMap::from([(ROOT.to_string(), Term::negative(root_version))]),
+            cause: Cause::Root,
+        });
+        
+        let mut next = Some(ROOT.to_string());
+        
+        while let Some(package) = next {
+            self.propagate(package)?;
+            next = self.choose_version().await?;
+        }
+        
+        Ok(self.assignments
+            .into_iter()
+            .filter(|assignment| assignment.cause.is_none() && assignment.package != ROOT)
+            .filter_map(|assignment| Some((assignment.package, assignment.term.versions.first()?.clone())))
+            .collect())
+    }
+    
+    /// Derive everything the incompatibilities imply after a package changed,
+    /// resolving any conflict on the way.
+    fn propagate(&mut self, package: String) -> Result<()> {
+        let mut changed = vec![package];
+        
+        while let Some(package) = changed.pop() {
+            let candidates = self.by_package.get(&package).cloned().unwrap_or_default();
+            
+            // Newer incompatibilities tend to be more specific, so check them first
+            for index in candidates.into_iter().rev() {
+                match self.implication(index) {
+                    Implication::Conflict => {
+                        let root_cause = self.resolve_conflict(index)?;
+                        changed.clear();
+                        
+                        if let Implication::Derive(name) = self.implication(root_cause) {
+                            self.derive(&name, root_cause);
+                            changed.push(name);
+                        }
+                        break;
+                    }
+                    Implication::Derive(name) => {
+                        self.derive(&name, index);
+                        
+                        if !changed.contains(&name) {
+                            changed.push(name);
+                        }
+                    }
+                    Implication::Nothing => {}
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Decide the next package.
+    ///
+    /// Picks the first package, by name, that must be selected but has no
+    /// version yet, and adds the dependencies of its most preferred allowed
+    /// version. The version is only decided if none of those dependencies
+    /// already conflicts; otherwise propagation rules it out.
+    ///
+    /// # Returns
+    /// The package that changed, or `None` once every package is decided
+    async fn choose_version(&mut self) -> Result<Option<String>> {
+        let Some((package, term)) = self.next_undecided() else {
+            return Ok(None);
+        };
+        
+        let version = self.versions(&package).await?
+            .into_iter()
+            .find(|version| term.versions.contains(version))
+            .expect("positive terms only contain offered versions");
+        
+        let added = self.add_dependencies(&package, &version).await?;
+        let conflicts = added.iter().any(|&index| {
+            self.incompatibilities[index].terms
+                .iter()
+                .filter(|(name, _)| **name != package)
+                .all(|(name, term)| self.relation(name, term) == Relation::Satisfied)
+        });
+        
+        if !conflicts {
+            self.assignments.push(Assignment {
+                package: package.clone(),
+                term: Term::positive(BTreeSet::from([version])),
+                decision_level: self.decision_level() + 1,
+                cause: None,
+            });
+        }
+        
+        Ok(Some(package))
+    }
+    
+    /// Find the first package, by name, that must be selected but is not decided
+    fn next_undecided(&self) -> Option<(String, Term)> {
+        let mut terms: BTreeMap<&str, Term> = BTreeMap::new();
+        let mut decided = HashSet::new();
+        
+        for assignment in &self.assignments {
+            if assignment.cause.is_none() {
+                decided.insert(assignment.package.as_str());
+            }
+            
+            terms.entry(&assignment.package)
+                .and_modify(|term| *term = term.intersect(&assignment.term))
+                .or_insert_with(|| assignment.term.clone());
+        }
+        
+        terms.into_iter()
+            .find(|(name, term)| term.positive && !decided.contains(name))
+            .map(|(name, term)| (name.to_string(), term))
+    }
+    
+    /// Get the versions of a package, most preferred first
+    async fn versions(&mut self, name: &str) -> Result<Vec<Version>> {
+        if let Some(versions) = self.versions.get(name) {
+            return Ok(versions.clone());
+        }
+        
+        let versions = self.provider.versions(name).await?;
+        self.versions.insert(name.to_string(), versions.clone());
+        
+        Ok(versions)
+    }
+    
+    /// Add an incompatibility for every dependency of a package version.
+    ///
+    /// # Returns
+    /// The indices of the added incompatibilities
+    async fn add_dependencies(&mut self, package: &str, version: &Version) -> Result<Vec<usize>> {
+        let dependencies = if package == ROOT {
+            self.root_requirements.clone()
+        } else {
+            self.provider.dependencies(package, version).await?
+        };
+        
+        let mut added = Vec::new();
+        
+        for (name, requirement) in dependencies {
+            let matching = self.versions(&name).await?
+                .into_iter()
+                .filter(|candidate| requirement.matches(candidate))
+                .collect();
+            
+            let selected = Term::positive(BTreeSet::from([version.clone()]));
+            let dependency = Term::negative(matching);
+            
+            let terms = if name == package {
+                // A package that depends on itself only rules out the
+                // versions the requirement excludes
+                let term = selected.intersect(&dependency);
+                
+                if term.is_empty() {
+                    continue;
+                }
+                
+                BTreeMap::from([(name.clone(), term)])
+            } else {
+                BTreeMap::from([(package.to_string(), selected), (name.clone(), dependency)])
+            };
+            
+            added.push(self.add_incompatibility(Incompatibility {
+                terms,
+                cause: Cause::Dependency {
+                    package: package.to_string(),
+                    version: version.clone(),
+                    dependency: name,
+                    requirement,
+                },
+            }));
+        }
+        
+        Ok(added)
+    }
+    
+    /// Add an incompatibility that propagation takes into account
+    fn add_incompatibility(&mut self, incompatibility: Incompatibility) -> usize {
+        let index = self.incompatibilities.len();
+        
+        for name in incompatibility.terms.keys() {
+            self.by_package.entry(name.clone()).or_default().push(index);
+        }
+        
+        self.incompatibilities.push(incompatibility);
+        index
+    }
+    
+    /// Number of decisions in the partial solution
+    fn decision_level(&self) -> usize {
+        self.assignments.iter().filter(|assignment| assignment.cause.is_none()).count()
+    }
+    
+    /// Intersection of every assignment to a package, if there are any
+    fn current(&self, package: &str) -> Option<Term> {
+        self.assignments
+            .iter()
+            .filter(|assignment| assignment.package == package)
+            .map(|assignment| assignment.term.clone())
+            .reduce(|current, term| current.intersect(&term))
+    }
+    
+    /// Relate the partial solution to a term
+    fn relation(&self, package: &str, term: &Term) -> Relation {
+        if term.is_any() {
+            return Relation::Satisfied;
+        }
+        
+        match self.current(package) {
+            Some(current) if current.satisfies(term) => Relation::Satisfied,
+            Some(current) if current.is_disjoint(term) => Relation::Contradicted,
+            _ => Relation::Inconclusive,
+        }
+    }
+    
+    /// Work out what the partial solution implies for an incompatibility
+    fn implication(&self, index: usize) -> Implication {
+        let mut unsatisfied = None;
+        
+        for (name, term) in &self.incompatibilities[index].terms {
+            match self.relation(name, term) {
+                Relation::Satisfied => {}
+                Relation::Contradicted => return Implication::Nothing,
+                Relation::Inconclusive if unsatisfied.is_some() => return Implication::Nothing,
+                Relation::Inconclusive => unsatisfied = Some(name.clone()),
+            }
+        }
+        
+        match unsatisfied {
+            Some(name) => Implication::Derive(name),
+            None => Implication::Conflict,
+        }
+    }
+    
+    /// Add the negation of an incompatibility's term for a package
+    fn derive(&mut self, package: &str, index: usize) {
+        let term = self.incompatibilities[index].terms[package].negate();
+        
+        self.assignments.push(Assignment {
+            package: package.to_string(),
+            term,
+            decision_level: self.decision_level(),
+            cause: Some(index),
+        });
+    }
+    
+    /// Find the earliest assignment after which the partial solution
+    /// satisfies a term.
+    ///
+    /// # Returns
+    /// The position of the assignment, or `None` if the term always holds
+    fn satisfier(&self, package: &str, term: &Term) -> Option<usize> {
+        if term.is_any() {
+            return None;
+        }
+        
+        let mut current: Option<Term> = None;
+        
+        for (position, assignment) in self.assignments.iter().enumerate() {
+            if assignment.package != package {
+                continue;
+            }
+            
+            let next = match current {
+                Some(current) => current.intersect(&assignment.term),
+                None => assignment.term.clone(),
+            };
+            
+            if next.satisfies(term) {
+                return Some(position);
+            }
+            
+            current = Some(next);
+        }
+        
+        None
+    }
+    
+    /// Check whether an incompatibility means that solving has failed
+    fn is_terminal(&self, index: usize) -> bool {
+        let mut terms = self.incompatibilities[index].terms
+            .iter()
+            .filter(|(_, term)| !term.is_any());
+        
+        match (terms.next(), terms.next()) {
+            (None, _) => true,
+            (Some((name, term)), None) => name == ROOT && term.positive,
+            _ => false,
+        }
+    }
+    
+    /// Resolve a conflict.
+    ///
+    /// Derives incompatibilities from the conflicting one until it is
+    /// caused by a single decision, learns the result, and backtracks to the
+    /// decision level where it is almost satisfied.
+    ///
+    /// # Returns
+    /// The learned incompatibility
+    fn resolve_conflict(&mut self, mut index: usize) -> Result<usize> {
+        let mut learned = false;
+        
+        loop {
+            if self.is_terminal(index) {
+                anyhow::bail!("Failed to resolve dependencies:\n{}", self.explain(index));
+            }
+            
+            let terms = self.incompatibilities[index].terms.clone();
+            
+            // The most recent satisfier of any term, and the highest
+            // decision level of the others
+            let mut most_recent: Option<(usize, &str)> = None;
+            let mut previous_level = 1;
+            
+            for (name, term) in &terms {
+                let Some(position) = self.satisfier(name, term) else {
+                    continue;
+                };
+                
+                let earlier = match most_recent {
+                    Some((recent, _)) if recent > position => position,
+                    Some((recent, _)) => {
+                        most_recent = Some((position, name));
+                        recent
+                    }
+                    None => {
+                        most_recent = Some((position, name));
+                        continue;
+                    }
+                };
+                
+                previous_level = previous_level.max(self.assignments[earlier].decision_level);
+            }
+            
+            let (position, package) = most_recent.expect("a conflict has a satisfier");
+            let satisfier = self.assignments[position].clone();
+            
+            // The part of the satisfier outside the term was already
+            // satisfied by an earlier assignment
+            let difference = satisfier.term.intersect(&terms[package].negate());
+            
+            if !difference.is_empty() {
+                if let Some(earlier) = self.satisfier(package, &difference.negate()) {
+                    previous_level = previous_level.max(self.assignments[earlier].decision_level);
+                }
+            }
+            
+            let Some(cause) = satisfier.cause.filter(|_| previous_level >= satisfier.decision_level) else {
+                self.backtrack(previous_level);
+                
+                if learned {
+                    for name in self.incompatibilities[index].terms.keys() {
+                        self.by_package.entry(name.clone()).or_default().push(index);
+                    }
+                }
+                
+                return Ok(index);
+            };
+            
+            // Replace the satisfier by the incompatibility it was derived from
+            let mut derived: BTreeMap<String, Term> = BTreeMap::new();
+            
+            for (name, term) in terms.iter().chain(&self.incompatibilities[cause].terms) {
+                if name == package {
+                    continue;
+                }
+                
+                derived.entry(name.clone())
+                    .and_modify(|existing| *existing = existing.intersect(term))
+                    .or_insert_with(|| term.clone());
+            }
+            
+            if !difference.is_empty() {
+                derived.insert(package.to_string(), difference.negate());
+            }
+            
+            derived.retain(|_, term| !term.is_any());
+            
+            self.incompatibilities.push(Incompatibility {
+                terms: derived,
+                cause: Cause::Derived(index, cause),
+            });
+            index = self.incompatibilities.len() - 1;
+            learned = true;
+        }
+    }
+    
+    /// Undo every assignment above a decision level
+    fn backtrack(&mut self, decision_level: usize) {
+        while self.assignments.last().is_some_and(|assignment| assignment.decision_level > decision_level) {
+            self.assignments.pop();
+        }
+    }
+    
+    /// Explain why an incompatibility holds.
+    ///
+    /// Every derived incompatibility is explained once, after the ones it
+    /// was derived from, as "Because x and y, z."
+    fn explain(&self, index: usize) -> String {
+        let mut lines = Vec::new();
+        
+        if matches!(self.incompatibilities[index].cause, Cause::Derived(..)) {
+            self.explain_derivation(index, &mut lines, &mut HashSet::new());
+        } else {
+            lines.push(format!("  {}", self.describe(index)));
+        }
+        
+        lines.join("\n")
+    }
+    
+    fn explain_derivation(&self, index: usize, lines: &mut Vec<String>, explained: &mut HashSet<usize>) {
+        let Cause::Derived(left, right) = self.incompatibilities[index].cause else {
+            return;
+        };
+        
+        if !explained.insert(index) {
+            return;
+        }
+        
+        self.explain_derivation(left, lines, explained);
+        self.explain_derivation(right, lines, explained);
+        
+        lines.push(format!(
+            "  Because {} and {}, {}.",
+            self.describe(left), self.describe(right), self.describe(index)
+        ));
+    }
+    
+    /// Describe an incompatibility as a sentence
+    fn describe(&self, index: usize) -> String {
+        let incompatibility = &self.incompatibilities[index];
+        
+        match &incompatibility.cause {
+            Cause::Root => format!("{} is selected", self.root),
+            Cause::Dependency { package, version, dependency, requirement } => {
+                let dependent = match package.as_str() {
+                    ROOT => self.root.clone(),
+                    _ => format!("{} {}", package, version),
+                };
+                
+                let matches_nothing = incompatibility.terms
+                    .get(dependency)
+                    .is_some_and(|term| term.is_any());
+                
+                if matches_nothing {
+                    format!(
+                        "{} depends on {} {}, which matches no version of {}",
+                        dependent, dependency, requirement, dependency
+                    )
+                } else {
+                    format!("{} depends on {} {}", dependent, dependency, requirement)
+                }
+            }
+            Cause::Derived(..) => {
+                let mut depends_on_root = false;
+                let mut positive = Vec::new();
+                let mut negative = Vec::new();
+                
+                for (name, term) in &incompatibility.terms {
+                    if name == ROOT && term.positive {
+                        depends_on_root = true;
+                    } else if term.positive {
+                        positive.push(self.describe_term(name, term));
+                    } else {
+                        negative.push(self.describe_term(name, term));
+                    }
+                }
+                
+                match (positive.as_slice(), negative.as_slice()) {
+                    ([], []) => "version solving failed".to_string(),
+                    ([term], []) => format!("{} is forbidden", term),
+                    ([], [term]) if depends_on_root => format!("{} depends on {}", self.root, term),
+                    ([], [term]) => format!("{} is required", term),
+                    ([dependent], [term]) => format!("{} depends on {}", dependent, term),
+                    ([first, second], []) => format!("{} is incompatible with {}", first, second),
+                    _ => {
+                        let terms = positive
+                            .into_iter()
+                            .chain(negative.into_iter().map(|term| format!("not {}", term)))
+                            .collect::<Vec<_>>();
+                        format!("{} are incompatible", terms.join(", "))
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Describe the versions of a term, ignoring its sign.
+    ///
+    /// A single selected version is shown as is; other sets are shown as the
+    /// requirement they came from where possible.
+    fn describe_term(&self, name: &str, term: &Term) -> String {
+        if name == ROOT {
+            return self.root.clone();
+        }
+        
+        if let (true, [version]) = (term.positive, term.versions.iter().collect::<Vec<_>>().as_slice()) {
+            return format!("{} {}", name, version);
+        }
+        
+        let requirement = self.incompatibilities.iter().find_map(|incompatibility| match &incompatibility.cause {
+            Cause::Dependency { dependency, requirement, .. }
+                if dependency == name
+                    && incompatibility.terms.get(name).is_some_and(|t| t.versions == term.versions) =>
+            {
+                Some(requirement)
+            }
+            _ => None,
+        });
+        
+        match requirement {
+            Some(requirement) => format!("{} {}", name, requirement),
+            None => format!(
+                "{} {}",
+                name,
+                term.versions.iter().map(ToString::to_string).collect::<Vec<_>>().join(" | ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{parse_requirement, parse_version};
+    
+    /// In-memory provider: name -> version -> dependencies
+    #[derive(Default)]
+    struct MemoryProvider {
+        packages: HashMap<String, BTreeMap<Version, Vec<(String, VersionReq)>>>,
+        /// Package versions whose dependencies were asked for, in order
+        visited: Vec<String>,
+    }
+    
+    impl MemoryProvider {
+        fn add(&mut self, name: &str, version: &str, deps: &[(&str, &str)]) {
+            let deps = deps.iter()
+                .map(|(n, r)| (n.to_string(), parse_requirement(r).unwrap()))
+                .collect();
+            self.packages
+                .entry(name.to_string())
+                .or_default()
+                .insert(parse_version(version).unwrap(), deps);
+        }
+    }
+    
+    impl DependencyProvider for MemoryProvider {
+        async fn versions(&mut self, name: &str) -> Result<Vec<Version>> {
+            Ok(self.packages.get(name)
+                .map(|versions| versions.keys().rev().cloned().collect())
+                .unwrap_or_default())
+        }
+        
+        async fn dependencies(&mut self, name: &str, version: &Version) -> Result<Vec<(String, VersionReq)>> {
+            self.visited.push(format!("{} {}", name, version));
+            Ok(self.packages[name][version].clone())
+        }
+    }
+    
+    fn root(deps: &[(&str, &str)]) -> Vec<(String, VersionReq)> {
+        deps.iter()
+            .map(|(n, r)| (n.to_string(), parse_requirement(r).unwrap()))
+            .collect()
+    }
+    
+    #[tokio::test]
+    async fn test_picks_highest_satisfying_all_requirements() {
+        let mut provider = MemoryProvider::default();
+        provider.add("a", "1.0.0", &[("c", ">=1.1")]);
+        provider.add("b", "1.0.0", &[("c", "<1.3")]);
+        for version in ["1.0.0", "1.1.0", "1.2.5", "1.3.0"] {
+            provider.add("c", version, &[]);
+        }
+        
+        let solution = Solver::new(&mut provider, "root")
+            .solve(root(&[("a", "^1"), ("b", "^1")]))
+            .await
+            .unwrap();
+        
+        assert_eq!(solution["c"].to_string(), "1.2.5");
+    }
+    
+    #[tokio::test]
+    async fn test_backtracks_on_incompatibility() {
+        let mut provider = MemoryProvider::default();
+        provider.add("a", "1.1.0", &[("c", "^2")]);
+        provider.add("a", "1.0.0", &[("c", "^1")]);
+        provider.add("b", "1.0.0", &[("c", "^1")]);
+        provider.add("c", "1.4.0", &[]);
+        provider.add("c", "2.0.0", &[]);
+        
+        let solution = Solver::new(&mut provider, "root")
+            .solve(root(&[("a", "^1"), ("b", "^1")]))
+            .await
+            .unwrap();
+        
+        assert_eq!(solution["a"].to_string(), "1.0.0");
+        assert_eq!(solution["c"].to_string(), "1.4.0");
+    }
+    
+    #[tokio::test]
+    async fn test_resolves_conflict_with_partial_satisfier() {
+        let mut provider = MemoryProvider::default();
+        provider.add("foo", "1.1.0", &[("left", "^1"), ("right", "^1")]);
+        provider.add("foo", "1.0.0", &[]);
+        provider.add("left", "1.0.0", &[("shared", ">=1")]);
+        provider.add("right", "1.0.0", &[("shared", "<2")]);
+        provider.add("shared", "2.0.0", &[]);
+        provider.add("shared", "1.0.0", &[("target", "^1")]);
+        provider.add("target", "2.0.0", &[]);
+        provider.add("target", "1.0.0", &[]);
+        
+        let solution = Solver::new(&mut provider, "root")
+            .solve(root(&[("foo", "^1"), ("target", "^2")]))
+            .await
+            .unwrap();
+        
+        assert_eq!(
+            solution.iter().map(|(name, version)| format!("{} {}", name, version)).collect::<Vec<_>>(),
+            vec!["foo 1.0.0", "target 2.0.0"]
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_learned_conflict_is_not_retried() {
+        let mut provider = MemoryProvider::default();
+        for minor in 0..10 {
+            provider.add("a", &format!("1.{}.0", minor), &[]);
+        }
+        provider.add("x", "1.0.0", &[("y", "^2")]);
+        provider.add("y", "1.0.0", &[]);
+        
+        let error = Solver::new(&mut provider, "root")
+            .solve(root(&[("a", "^1"), ("x", "^1")]))
+            .await
+            .unwrap_err()
+            .to_string();
+        
+        // The conflict does not involve a, so no other version of a is tried
+        assert_eq!(provider.visited, vec!["a 1.9.0", "x 1.0.0"]);
+        assert!(error.contains("x 1.0.0 depends on y ^2, which matches no version of y"));
+        assert!(error.ends_with("version solving failed."));
+    }
+    
+    #[tokio::test]
+    async fn test_conflict_explanation() {
+        let mut provider = MemoryProvider::default();
+        provider.add("a", "1.0.0", &[("c", "^2")]);
+        provider.add("b", "0.3.0", &[("c", "^1")]);
+        provider.add("c", "1.0.0", &[]);
+        provider.add("c", "2.0.0", &[]);
+        
+        let error = Solver::new(&mut provider, "root")
+            .solve(root(&[("a", "^1"), ("b", "^0.3")]))
+            .await
+            .unwrap_err()
+            .to_string();
+        
+        assert_eq!(
+            error,
+            "Failed to resolve dependencies:\n\
+             \x20 Because a 1.0.0 depends on c ^2 and b 0.3.0 depends on c ^1, a 1.0.0 is incompatible with b 0.3.0.\n\
+             \x20 Because a 1.0.0 is incompatible with b 0.3.0 and root depends on a ^1, b 0.3.0 is forbidden.\n\
+             \x20 Because b 0.3.0 is forbidden and root depends on b ^0.3, version solving failed."
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_multi_level_conflict_explanation() {
+        let mut provider = MemoryProvider::default();
+        provider.add("a", "1.0.0", &[("d", "^1")]);
+        provider.add("b", "0.3.0", &[("c", "^1")]);
+        provider.add("c", "1.0.0", &[]);
+        provider.add("c", "2.0.0", &[]);
+        provider.add("d", "1.0.0", &[("c", "^2")]);
+        
+        let error = Solver::new(&mut provider, "root")
+            .solve(root(&[("a", "^1"), ("b", "^0.3")]))
+            .await
+            .unwrap_err()
+            .to_string();
+        
+        let lines = error.lines().map(str::trim).collect::<Vec<_>>();
+        assert_eq!(
+            lines[1..],
+            [
+                "Because a 1.0.0 depends on d ^1 and d 1.0.0 depends on c ^2, a 1.0.0 depends on c ^2.",
+                "Because a 1.0.0 depends on c ^2 and b 0.3.0 depends on c ^1, a 1.0.0 is incompatible with b 0.3.0.",
+                "Because a 1.0.0 is incompatible with b 0.3.0 and root depends on a ^1, b 0.3.0 is forbidden.",
+                "Because b 0.3.0 is forbidden and root depends on b ^0.3, version solving failed.",
+            ]
+        );
+    }
+}
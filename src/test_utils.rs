@@ -0,0 +1,96 @@
+//! # Test Utilities
+//!
+//! Fixtures shared by the unit tests.
+
+use crate::cache;
+use crate::config::{Config, DEFAULT_REGISTRY_SOURCE};
+use crate::dependency::DependencyResolver;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Serializes tests that change the working directory of the process
+static CURRENT_DIR: Mutex<()> = Mutex::new(());
+
+/// Working directory of the process, changed for the duration of a test.
+///
+/// The previous directory is restored on drop, so declare the guard after
+/// the temporary directory it points into; otherwise git and other child
+/// processes started by concurrent tests run in a deleted directory.
+pub struct CurrentDir {
+    previous: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl CurrentDir {
+    /// Change the working directory until the guard is dropped
+    pub fn change(dir: &Path) -> Self {
+        let lock = CURRENT_DIR.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir).unwrap();
+        
+        Self { previous, _lock: lock }
+    }
+}
+
+impl Drop for CurrentDir {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(&self.previous);
+    }
+}
+
+/// Write a package manifest and create its `src` directory.
+///
+/// # Arguments
+/// * `dir` - Root directory of the package
+/// * `name` - The package name
+/// * `tables` - Manifest tables after `[package]`, e.g. `[dependencies]`
+pub fn write_package(dir: &Path, name: &str, tables: &str) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Quantum.toml"),
+        format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n{}", name, tables),
+    ).unwrap();
+}
+
+/// Create a dependency resolver with its own cache directory and an empty
+/// configuration, so that tests neither touch the user's cache nor depend
+/// on their config.toml.
+///
+/// # Arguments
+/// * `cache_dir` - Cache directory, normally inside a temporary directory
+pub fn isolated_resolver(cache_dir: &Path) -> DependencyResolver {
+    DependencyResolver::with_config(None, Config::default(), cache_dir.to_path_buf()).unwrap()
+}
+
+/// Build the registry archive of a package without dependencies
+pub fn package_archive(name: &str, version: &str) -> Vec<u8> {
+    let manifest = format!("[package]\nname = \"{}\"\nversion = \"{}\"\n", name, version);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    
+    let mut archive = tar::Builder::new(Vec::new());
+    archive.append_data(&mut header, "Quantum.toml", manifest.as_bytes()).unwrap();
+    archive.into_inner().unwrap()
+}
+
+/// Put a registry package into the cache as if it had been downloaded
+pub fn cache_package(resolver: &DependencyResolver, name: &str, version: &str) {
+    let archive = package_archive(name, version);
+    cache::write_atomic(&resolver.registry_archive_path(DEFAULT_REGISTRY_SOURCE, name, version), &archive).unwrap();
+    cache::extract_archive(&archive, &resolver.registry_cache_path(DEFAULT_REGISTRY_SOURCE, name, version)).unwrap();
+}
+
+/// Run git in a test fixture repository and return its output
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "protocol.file.allow=always"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
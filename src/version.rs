@@ -0,0 +1,57 @@
+//! # Version Requirements
+//!
+//! Semver parsing and matching for dependency version requirements.
+
+use anyhow::{Context, Result};
+
+pub use semver::{Version, VersionReq};
+
+/// Parse a version requirement from a manifest.
+///
+/// Supports caret (`^1.2`), tilde (`~0.3.1`), wildcard (`1.*`) and
+/// comparison (`>=0.3, <0.5`) requirements. A bare version such as
+/// `1.2.3` is treated as a caret requirement.
+pub fn parse_requirement(requirement: &str) -> Result<VersionReq> {
+    VersionReq::parse(requirement.trim())
+        .with_context(|| format!("Invalid version requirement: {}", requirement))
+}
+
+/// Parse a concrete package version
+pub fn parse_version(version: &str) -> Result<Version> {
+    Version::parse(version.trim())
+        .with_context(|| format!("Invalid version: {}", version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn highest(requirement: &str, available: &[&str]) -> Option<String> {
+        let requirement = parse_requirement(requirement).unwrap();
+        
+        available.iter()
+            .map(|v| parse_version(v).unwrap())
+            .filter(|v| requirement.matches(v))
+            .max()
+            .map(|v| v.to_string())
+    }
+    
+    #[test]
+    fn test_requirement_kinds() {
+        let available = ["0.3.0", "0.4.7", "0.5.0", "1.2.0", "1.9.3", "2.0.0"];
+        
+        assert_eq!(highest("^1.2", &available).as_deref(), Some("1.9.3"));
+        assert_eq!(highest("1.2.0", &available).as_deref(), Some("1.9.3"));
+        assert_eq!(highest("~0.4", &available).as_deref(), Some("0.4.7"));
+        assert_eq!(highest("1.*", &available).as_deref(), Some("1.9.3"));
+        assert_eq!(highest(">=0.3, <0.5", &available).as_deref(), Some("0.4.7"));
+        assert_eq!(highest("=1.2.0", &available).as_deref(), Some("1.2.0"));
+        assert_eq!(highest("^3", &available), None);
+    }
+    
+    #[test]
+    fn test_invalid_requirement() {
+        assert!(parse_requirement("not a version").is_err());
+        assert!(parse_version("1.2").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! # Workspaces
+//!
+//! A workspace is a set of packages that share one Quantum.lock and one
+//! build directory. Its root Quantum.toml has a `[workspace]` table and may
+//! also describe a package of its own. A package outside any workspace is
+//! treated as a workspace with a single member.
+
+use crate::dependency::{DependencyResolver, ResolveOptions, ResolvedDependencies};
+use crate::lockfile::Lockfile;
+use crate::manifest::Dependency;
+use crate::package::Package;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Workspace configuration (`[workspace]` table)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    /// Member directories relative to the workspace root; a final `*`
+    /// component matches every subdirectory containing a Quantum.toml
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Directories excluded from the members
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Package metadata members inherit with `field.workspace = true`
+    #[serde(default)]
+    pub package: toml::Table,
+    /// Dependencies members inherit with `{ workspace = true }`
+    #[serde(default)]
+    pub dependencies: HashMap<String, Dependency>,
+}
+
+/// The workspace-level keys of a root Quantum.toml
+#[derive(Deserialize)]
+struct RootManifest {
+    #[serde(default)]
+    workspace: Option<WorkspaceConfig>,
+    #[serde(default)]
+    patch: HashMap<String, HashMap<String, Dependency>>,
+    #[serde(default)]
+    package: Option<toml::Value>,
+}
+
+impl RootManifest {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+/// Packages selected on the command line
+#[derive(Debug, Clone, Default)]
+pub struct PackageSelection {
+    /// Packages selected with `-p`
+    pub packages: Vec<String>,
+    /// Select every member (`--workspace`)
+    pub workspace: bool,
+}
+
+/// A workspace and its member packages
+pub struct Workspace {
+    /// Root directory, holding the shared Quantum.lock and build directory
+    pub root: PathBuf,
+    /// Member packages, ordered by name
+    pub members: Vec<Package>,
+    /// `[patch]` tables of the root manifest
+    pub patch: HashMap<String, HashMap<String, Dependency>>,
+    /// Whether the root manifest has a `[workspace]` table
+    is_workspace: bool,
+    /// Index of the member in the directory the workspace was loaded from
+    current: Option<usize>,
+}
+
+impl Workspace {
+    /// Load the workspace containing the current directory
+    pub fn load_current() -> Result<Self> {
+        let current_dir = std::env::current_dir()
+            .context("Failed to get current directory")?;
+        
+        Self::load(&current_dir)
+    }
+    
+    /// Load the workspace containing a directory.
+    ///
+    /// The nearest ancestor with a `[workspace]` table is used if `dir` is
+    /// its root or one of its members; otherwise `dir` must be a package.
+    ///
+    /// # Arguments
+    /// * `dir` - The workspace root or a member package directory
+    pub fn load(dir: &Path) -> Result<Self> {
+        let dir = dir.canonicalize()
+            .with_context(|| format!("Failed to access {}", dir.display()))?;
+        
+        for ancestor in dir.ancestors() {
+            let manifest_path = ancestor.join("Quantum.toml");
+            
+            if !manifest_path.exists() {
+                continue;
+            }
+            
+            let manifest = RootManifest::load(&manifest_path)?;
+            
+            if manifest.workspace.is_none() {
+                continue;
+            }
+            
+            let workspace = Self::load_root(ancestor, manifest, &dir)?;
+            
+            if ancestor == dir || workspace.current.is_some() {
+                return Ok(workspace);
+            }
+        }
+        
+        let package = Package::load(&dir)?;
+        
+        Ok(Self {
+            root: dir,
+            patch: package.manifest.patch.clone(),
+            members: vec![package],
+            is_workspace: false,
+            current: Some(0),
+        })
+    }
+    
+    /// Load the members of a workspace root
+    fn load_root(root: &Path, manifest: RootManifest, current_dir: &Path) -> Result<Self> {
+        let config = manifest.workspace.unwrap_or_default();
+        let mut dirs = BTreeSet::new();
+        
+        if manifest.package.is_some() {
+            dirs.insert(root.to_path_buf());
+        }
+        
+        for pattern in &config.members {
+            dirs.extend(expand_members(root, pattern)?);
+        }
+        
+        let excluded = config.exclude
+            .iter()
+            .map(|exclude| root.join(exclude).canonicalize().unwrap_or_else(|_| root.join(exclude)))
+            .collect::<Vec<_>>();
+        dirs.retain(|dir| !excluded.iter().any(|exclude| dir.starts_with(exclude)));
+        
+        let mut members = dirs
+            .into_iter()
+            .map(Package::load)
+            .collect::<Result<Vec<_>>>()?;
+        members.sort_by(|a, b| a.name().cmp(b.name()));
+        
+        for pair in members.windows(2) {
+            if pair[0].name() == pair[1].name() {
+                anyhow::bail!(
+                    "Workspace members {} and {} are both named {}",
+                    pair[0].root.display(), pair[1].root.display(), pair[0].name()
+                );
+            }
+        }
+        
+        for member in members.iter().filter(|member| member.root != root && !member.manifest.patch.is_empty()) {
+            println!(
+                "{} [patch] in {} is ignored; patches only apply in the workspace root {}",
+                "warning:".yellow().bold(),
+                member.root.join("Quantum.toml").display(),
+                root.join("Quantum.toml").display()
+            );
+        }
+        
+        let current = members.iter().position(|member| member.root == current_dir);
+        
+        Ok(Self {
+            root: root.to_path_buf(),
+            members,
+            patch: manifest.patch,
+            is_workspace: true,
+            current,
+        })
+    }
+    
+    /// Whether this is a workspace rather than a single package
+    pub fn is_workspace(&self) -> bool {
+        self.is_workspace
+    }
+    
+    /// Get the member in the directory the workspace was loaded from
+    pub fn current(&self) -> Option<&Package> {
+        self.current.map(|index| &self.members[index])
+    }
+    
+    /// Get a member by name
+    pub fn member(&self, name: &str) -> Option<&Package> {
+        self.members.iter().find(|member| member.name() == name)
+    }
+    
+    /// Get the members selected on the command line.
+    ///
+    /// Without `-p` or `--workspace` this is the member in the current
+    /// directory, or every member when run from a workspace root that is not
+    /// a package itself.
+    pub fn selected(&self, selection: &PackageSelection) -> Result<Vec<&Package>> {
+        if selection.workspace {
+            return Ok(self.members.iter().collect());
+        }
+        
+        if !selection.packages.is_empty() {
+            return selection.packages
+                .iter()
+                .map(|name| self.member(name).ok_or_else(|| {
+                    anyhow::anyhow!("Package {} is not a member of the workspace", name)
+                }))
+                .collect();
+        }
+        
+        Ok(match self.current() {
+            Some(package) => vec![package],
+            None => self.members.iter().collect(),
+        })
+    }
+    
+    /// Get the shared Quantum.lock path
+    pub fn lockfile_path(&self) -> PathBuf {
+        self.root.join("Quantum.lock")
+    }
+    
+    /// Get the shared build directory
+    pub fn build_dir(&self, release: bool) -> PathBuf {
+        let profile = if release { "release" } else { "debug" };
+        self.root.join("build").join(profile)
+    }
+    
+    /// Check whether any member has dependencies
+    pub fn has_dependencies(&self) -> bool {
+        self.members.iter().any(|member| !member.manifest.all_dependencies().is_empty())
+    }
+    
+    /// Resolve the dependencies of every member and update Quantum.lock.
+    ///
+    /// # Arguments
+    /// * `resolver` - The resolver to use; the existing lockfile and the
+    ///   offline option are applied to it
+    /// * `options` - How Quantum.lock may be used and updated
+    pub async fn resolve(&self, resolver: DependencyResolver, options: ResolveOptions) -> Result<ResolvedDependencies> {
+        let lockfile_path = self.lockfile_path();
+        let existing = Lockfile::load_if_exists(&lockfile_path)?;
+        
+        if options.locked && existing.is_none() {
+            anyhow::bail!("Quantum.lock is missing and --locked was passed");
+        }
+        
+        let resolver = resolver
+            .with_lockfile(existing.clone())
+            .offline(options.offline);
+        let resolved = resolver.resolve(self).await?;
+        
+        // Save lockfile
+        let lockfile = Lockfile::from_resolved(&resolved);
+        
+        if existing.as_ref() != Some(&lockfile) {
+            if options.locked {
+                anyhow::bail!("Quantum.lock needs to be updated but --locked was passed");
+            }
+            
+            lockfile.save(&lockfile_path)?;
+        }
+        
+        Ok(resolved)
+    }
+}
+
+/// Find the nearest workspace root at or above a directory.
+///
+/// # Returns
+/// The workspace root directory and its `[workspace]` table
+pub fn find_root(dir: &Path) -> Result<Option<(PathBuf, WorkspaceConfig)>> {
+    for ancestor in dir.ancestors() {
+        let manifest_path = ancestor.join("Quantum.toml");
+        
+        if !manifest_path.exists() {
+            continue;
+        }
+        
+        if let Some(config) = RootManifest::load(&manifest_path)?.workspace {
+            return Ok(Some((ancestor.to_path_buf(), config)));
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Expand a `members` entry into canonical package directories
+fn expand_members(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let Some(parent) = pattern.strip_suffix('*').map(|p| p.trim_end_matches('/')) else {
+        let dir = root.join(pattern);
+        let canonical = dir.canonicalize()
+            .with_context(|| format!("Workspace member {} not found", dir.display()))?;
+        return Ok(vec![canonical]);
+    };
+    
+    let parent = root.join(parent);
+    let mut dirs = Vec::new();
+    
+    for entry in std::fs::read_dir(&parent)
+        .with_context(|| format!("Failed to read {}", parent.display()))?
+    {
+        let path = entry?.path();
+        
+        if path.join("Quantum.toml").exists() {
+            dirs.push(path.canonicalize()?);
+        }
+    }
+    
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::write_package;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_workspace_members() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        
+        std::fs::write(
+            root.join("Quantum.toml"),
+            "[workspace]\nmembers = [\"contracts/*\", \"tools\"]\nexclude = [\"contracts/old\"]\n",
+        ).unwrap();
+        write_package(&root.join("contracts/token"), "token", "");
+        write_package(&root.join("contracts/market"), "market", "");
+        write_package(&root.join("contracts/old"), "old", "");
+        write_package(&root.join("tools"), "tools", "");
+        
+        let workspace = Workspace::load(root).unwrap();
+        let names = workspace.members.iter().map(Package::name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["market", "token", "tools"]);
+        assert!(workspace.current().is_none());
+        assert_eq!(workspace.selected(&PackageSelection::default()).unwrap().len(), 3);
+        
+        let member = Workspace::load(&root.join("contracts/token")).unwrap();
+        assert_eq!(member.root, workspace.root);
+        assert_eq!(member.current().unwrap().name(), "token");
+        
+        let selection = PackageSelection { packages: vec!["tools".to_string()], workspace: false };
+        assert_eq!(member.selected(&selection).unwrap()[0].name(), "tools");
+        
+        let selection = PackageSelection { packages: vec!["old".to_string()], workspace: false };
+        assert!(member.selected(&selection).is_err());
+        
+        // Excluded directories are single packages
+        let old = Workspace::load(&root.join("contracts/old")).unwrap();
+        assert!(!old.is_workspace());
+    }
+}